@@ -91,6 +91,7 @@ def main():
                 "title": title,
                 "episode_id": ep_id,
                 "director": film['director'],
+                "producer": film['producer'],
                 "release_date": film['release_date'],
                 "opening_crawl": film['opening_crawl'].replace("\r\n", " "),
                 "wiki_plot": wiki_summary,
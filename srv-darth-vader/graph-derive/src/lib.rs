@@ -0,0 +1,501 @@
+// Derive de `GraphableSource` para los modelos crudos de srv-darth-vader.
+//
+// #[derive(GraphableSource)]
+// #[graph(label = "Starship")]
+// pub struct StarshipRaw {
+//     #[graph(oid)] pub _id: ObjectId,                 // -> original_oid (hex)
+//     #[graph(id)] pub id: String,                     // id del nodo
+//     #[graph(name)] pub name: String,                 // nombre (también propiedad)
+//     #[graph(rich_text)] pub wiki_description: String,
+//     #[graph(edge(relation = "PILOTS", target = "Character", prefix = "char_", direction = "incoming"))]
+//     pub pilot_ids: Vec<String>,
+//     #[graph(range)] pub crew: String,                // crew, crew_min, crew_max, crew_raw
+//     #[graph(number(unit = "credits"))] pub cost_in_credits: String,
+//     #[graph(split(label = "Manufacturer", relation = "MANUFACTURED_BY", prefix = "manufacturer_"))]
+//     pub manufacturer: String,                        // "A, B" -> dos nodos :Manufacturer
+//                                                      // (`always`: ignora SPLIT_ATTRIBUTES)
+//     #[graph(date)] pub release_date: String,         // date de Neo4j + release_date_raw
+//     #[graph(skip)] pub internal: String,             // ni propiedad ni edge
+//     pub model: String,                               // propiedad con su tipo serde
+// }
+//
+// El código generado referencia `crate::models`, así que solo sirve dentro del servicio.
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Fields, Ident, LitStr, parse_macro_input, spanned::Spanned};
+
+#[proc_macro_derive(GraphableSource, attributes(graph))]
+pub fn derive_graphable_source(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+struct SplitAttr {
+    label: LitStr,
+    relation: Ident,
+    prefix: String,
+    incoming: bool,
+    // Se extrae aunque el label no esté en SPLIT_ATTRIBUTES
+    always: bool,
+}
+
+struct EdgeAttr {
+    relation: Ident,
+    target: LitStr,
+    prefix: String,
+    incoming: bool,
+}
+
+enum NumericKind {
+    Number,
+    Range,
+}
+
+struct Numeric {
+    kind: NumericKind,
+    unit: Option<String>,
+}
+
+#[derive(Default)]
+struct FieldAttrs {
+    id: bool,
+    name: bool,
+    rich_text: bool,
+    skip: bool,
+    oid: bool,
+    rename: Option<String>,
+    numeric: Option<Numeric>,
+    date: bool,
+    edges: Vec<EdgeAttr>,
+    splits: Vec<SplitAttr>,
+}
+
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let ident = &input.ident;
+    let label = container_label(&input)?;
+
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new(
+            input.span(),
+            "GraphableSource solo se deriva para structs",
+        ));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new(
+            input.span(),
+            "GraphableSource requiere campos con nombre",
+        ));
+    };
+
+    let mut id_field = None;
+    let mut name_field = None;
+    let mut rich_text = Vec::new();
+    let mut properties = Vec::new();
+    let mut edges = Vec::new();
+    let mut dates = Vec::new();
+
+    for field in &fields.named {
+        let field_ident = field.ident.clone().expect("campos con nombre");
+        let attrs = field_attrs(field)?;
+
+        if attrs.id {
+            if id_field.is_some() {
+                return Err(syn::Error::new(field.span(), "#[graph(id)] repetido"));
+            }
+            id_field = Some(field_ident.clone());
+        }
+        if attrs.name {
+            if name_field.is_some() {
+                return Err(syn::Error::new(field.span(), "#[graph(name)] repetido"));
+            }
+            name_field = Some(field_ident.clone());
+        }
+        if attrs.rich_text {
+            rich_text.push(field_ident.clone());
+        }
+
+        for edge in &attrs.edges {
+            edges.push(expand_edge(&field_ident, edge));
+        }
+        for split in &attrs.splits {
+            edges.push(expand_split(&field_ident, split));
+        }
+
+        // id, rich_text y skip no se copian como propiedades
+        if attrs.oid {
+            properties.push(quote! {
+                map.insert(
+                    "original_oid".to_string(),
+                    ::serde_json::Value::String(self.#field_ident.to_hex()),
+                );
+            });
+        } else if !(attrs.id || attrs.rich_text || attrs.skip) {
+            let key = attrs.rename.unwrap_or_else(|| field_ident.to_string());
+            if attrs.date {
+                let raw_key = format!("{}_raw", key);
+                properties.push(quote! {
+                    map.insert(#raw_key.to_string(), ::serde_json::Value::from(self.#field_ident.as_str()));
+                });
+                dates.push(quote! {
+                    (#key.to_string(), crate::normalize::parse_date(&self.#field_ident))
+                });
+                continue;
+            }
+            properties.push(match attrs.numeric {
+                Some(numeric) => {
+                    let unit = match numeric.unit {
+                        Some(unit) => quote!(Some(#unit)),
+                        None => quote!(None),
+                    };
+                    let insert = match numeric.kind {
+                        NumericKind::Number => quote!(crate::normalize::insert_number),
+                        NumericKind::Range => quote!(crate::normalize::insert_range),
+                    };
+                    quote! {
+                        #insert(&mut map, #key, &self.#field_ident, #unit);
+                    }
+                }
+                None => quote! {
+                    map.insert(#key.to_string(), crate::models::to_property(&self.#field_ident));
+                },
+            });
+        }
+    }
+
+    let id_field =
+        id_field.ok_or_else(|| syn::Error::new(input.span(), "Falta un campo con #[graph(id)]"))?;
+    let name_field = name_field
+        .ok_or_else(|| syn::Error::new(input.span(), "Falta un campo con #[graph(name)]"))?;
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics crate::models::GraphableSource for #ident #ty_generics #where_clause {
+            fn get_entity_id(&self) -> String {
+                self.#id_field.to_string()
+            }
+
+            fn get_entity_label(&self) -> crate::models::Label {
+                crate::models::Label::from_static(#label)
+            }
+
+            fn get_entity_name(&self) -> String {
+                self.#name_field.to_string()
+            }
+
+            fn get_metadata_as_map(&self) -> ::serde_json::Map<String, ::serde_json::Value> {
+                let mut map = ::serde_json::Map::new();
+                #(#properties)*
+                map
+            }
+
+            fn get_rich_text(&self) -> String {
+                crate::models::join_rich_text(&[#(self.#rich_text.as_str()),*])
+            }
+
+            fn get_date_properties(&self) -> Vec<(String, Option<::chrono::NaiveDate>)> {
+                vec![#(#dates),*]
+            }
+
+            fn get_edges(&self) -> Vec<crate::models::GraphEdge> {
+                let mut edges = Vec::new();
+                let entity_id = self.#id_field.to_string();
+                let entity_label = crate::models::Label::from_static(#label);
+                #(#edges)*
+                edges
+            }
+        }
+    })
+}
+
+fn expand_edge(field: &Ident, edge: &EdgeAttr) -> proc_macro2::TokenStream {
+    let relation = &edge.relation;
+    let target = &edge.target;
+    let prefix = &edge.prefix;
+
+    let (source_id, source_label, target_id, target_label) = if edge.incoming {
+        (
+            quote!(other_id),
+            quote!(crate::models::Label::from_static(#target)),
+            quote!(entity_id.clone()),
+            quote!(entity_label.clone()),
+        )
+    } else {
+        (
+            quote!(entity_id.clone()),
+            quote!(entity_label.clone()),
+            quote!(other_id),
+            quote!(crate::models::Label::from_static(#target)),
+        )
+    };
+
+    quote! {
+        for value in crate::models::EdgeIds::edge_ids(&self.#field) {
+            let other_id = format!("{}{}", #prefix, value);
+            edges.push(crate::models::GraphEdge {
+                source_id: #source_id,
+                source_label: #source_label,
+                target_id: #target_id,
+                relation_type: crate::models::RelationType::#relation,
+                target_label: #target_label,
+                source_name: None,
+                target_name: None,
+                properties: ::serde_json::Map::new(),
+            });
+        }
+    }
+}
+
+// Un nodo por valor del atributo, con id derivado del nombre normalizado
+fn expand_split(field: &Ident, split: &SplitAttr) -> proc_macro2::TokenStream {
+    let label = &split.label;
+    let relation = &split.relation;
+    let prefix = &split.prefix;
+
+    // El nodo extraído lleva su nombre, sea origen o destino de la relación
+    let edge = if split.incoming {
+        quote! {
+            crate::models::GraphEdge {
+                source_id: format!("{}{}", #prefix, slug),
+                source_label: crate::models::Label::from_static(#label),
+                target_id: entity_id.clone(),
+                relation_type: crate::models::RelationType::#relation,
+                target_label: entity_label.clone(),
+                source_name: Some(name),
+                target_name: None,
+                properties: ::serde_json::Map::new(),
+            }
+        }
+    } else {
+        quote! {
+            crate::models::GraphEdge {
+                source_id: entity_id.clone(),
+                source_label: entity_label.clone(),
+                target_id: format!("{}{}", #prefix, slug),
+                relation_type: crate::models::RelationType::#relation,
+                target_label: crate::models::Label::from_static(#label),
+                source_name: None,
+                target_name: Some(name),
+                properties: ::serde_json::Map::new(),
+            }
+        }
+    };
+
+    let extract = quote! {
+        for (slug, name) in crate::normalize::split_attribute(&self.#field) {
+            edges.push(#edge);
+        }
+    };
+    if split.always {
+        extract
+    } else {
+        quote! {
+            if crate::normalize::split_enabled(#label) {
+                #extract
+            }
+        }
+    }
+}
+
+fn container_label(input: &DeriveInput) -> syn::Result<LitStr> {
+    let mut label = None;
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("graph")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("label") {
+                let value: LitStr = meta.value()?.parse()?;
+                check_identifier(&value)?;
+                label = Some(value);
+                Ok(())
+            } else {
+                Err(meta.error("atributo de struct desconocido, se esperaba label"))
+            }
+        })?;
+    }
+    label.ok_or_else(|| {
+        syn::Error::new(
+            input.ident.span(),
+            "Falta #[graph(label = \"...\")] en el struct",
+        )
+    })
+}
+
+fn field_attrs(field: &syn::Field) -> syn::Result<FieldAttrs> {
+    let mut attrs = FieldAttrs::default();
+
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("graph")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("id") {
+                attrs.id = true;
+            } else if meta.path.is_ident("name") {
+                attrs.name = true;
+            } else if meta.path.is_ident("rich_text") {
+                attrs.rich_text = true;
+            } else if meta.path.is_ident("skip") {
+                attrs.skip = true;
+            } else if meta.path.is_ident("date") {
+                attrs.date = true;
+            } else if meta.path.is_ident("oid") {
+                attrs.oid = true;
+            } else if meta.path.is_ident("number") || meta.path.is_ident("range") {
+                let kind = if meta.path.is_ident("number") {
+                    NumericKind::Number
+                } else {
+                    NumericKind::Range
+                };
+                let mut unit = None;
+                if meta.input.peek(syn::token::Paren) {
+                    meta.parse_nested_meta(|numeric| {
+                        if numeric.path.is_ident("unit") {
+                            let value: LitStr = numeric.value()?.parse()?;
+                            unit = Some(value.value());
+                            Ok(())
+                        } else {
+                            Err(numeric.error("se esperaba unit"))
+                        }
+                    })?;
+                }
+                attrs.numeric = Some(Numeric { kind, unit });
+            } else if meta.path.is_ident("rename") {
+                let value: LitStr = meta.value()?.parse()?;
+                check_identifier(&value)?;
+                attrs.rename = Some(value.value());
+            } else if meta.path.is_ident("split") {
+                let mut label = None;
+                let mut relation = None;
+                let mut prefix = String::new();
+                let mut incoming = false;
+                let mut always = false;
+
+                meta.parse_nested_meta(|split| {
+                    if split.path.is_ident("always") {
+                        always = true;
+                        return Ok(());
+                    }
+                    let value: LitStr = split.value()?.parse()?;
+                    if split.path.is_ident("label") {
+                        check_identifier(&value)?;
+                        label = Some(value);
+                    } else if split.path.is_ident("relation") {
+                        check_identifier(&value)?;
+                        relation =
+                            Some(Ident::new(&relation_variant(&value.value()), value.span()));
+                    } else if split.path.is_ident("prefix") {
+                        prefix = value.value();
+                    } else if split.path.is_ident("direction") {
+                        incoming = parse_direction(&value)?;
+                    } else {
+                        return Err(
+                            split.error("se esperaba label, relation, prefix, direction o always")
+                        );
+                    }
+                    Ok(())
+                })?;
+
+                attrs.splits.push(SplitAttr {
+                    label: label.ok_or_else(|| meta.error("split sin label"))?,
+                    relation: relation.ok_or_else(|| meta.error("split sin relation"))?,
+                    prefix,
+                    incoming,
+                    always,
+                });
+            } else if meta.path.is_ident("edge") {
+                let mut relation = None;
+                let mut target = None;
+                let mut prefix = String::new();
+                let mut incoming = false;
+
+                meta.parse_nested_meta(|edge| {
+                    let value: LitStr = edge.value()?.parse()?;
+                    if edge.path.is_ident("relation") {
+                        check_identifier(&value)?;
+                        relation =
+                            Some(Ident::new(&relation_variant(&value.value()), value.span()));
+                    } else if edge.path.is_ident("target") {
+                        check_identifier(&value)?;
+                        target = Some(value);
+                    } else if edge.path.is_ident("prefix") {
+                        prefix = value.value();
+                    } else if edge.path.is_ident("direction") {
+                        incoming = parse_direction(&value)?;
+                    } else {
+                        return Err(edge.error("se esperaba relation, target, prefix o direction"));
+                    }
+                    Ok(())
+                })?;
+
+                attrs.edges.push(EdgeAttr {
+                    relation: relation.ok_or_else(|| meta.error("edge sin relation"))?,
+                    target: target.ok_or_else(|| meta.error("edge sin target"))?,
+                    prefix,
+                    incoming,
+                });
+            } else {
+                return Err(meta.error("atributo de campo desconocido"));
+            }
+            Ok(())
+        })?;
+    }
+
+    Ok(attrs)
+}
+
+fn parse_direction(value: &LitStr) -> syn::Result<bool> {
+    match value.value().as_str() {
+        "outgoing" => Ok(false),
+        "incoming" => Ok(true),
+        _ => Err(syn::Error::new(
+            value.span(),
+            "direction debe ser \"outgoing\" o \"incoming\"",
+        )),
+    }
+}
+
+// Labels y relaciones terminan interpolados en Cypher: se validan al compilar
+fn check_identifier(value: &LitStr) -> syn::Result<()> {
+    let text = value.value();
+    let mut chars = text.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(syn::Error::new(
+            value.span(),
+            format!("{:?} no es un identificador válido", text),
+        ))
+    }
+}
+
+// "APPEARED_IN" -> AppearedIn; una relación desconocida falla al compilar
+fn relation_variant(relation: &str) -> String {
+    relation
+        .split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let lower = part.to_lowercase();
+            let mut chars = lower.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::relation_variant;
+
+    #[test]
+    fn relation_variant_is_pascal_case() {
+        assert_eq!(relation_variant("APPEARED_IN"), "AppearedIn");
+        assert_eq!(relation_variant("HAS_STARSHIP_CLASS"), "HasStarshipClass");
+        assert_eq!(relation_variant("PILOTS"), "Pilots");
+        assert_eq!(relation_variant("born_on"), "BornOn");
+        assert_eq!(relation_variant("_BORN__ON_"), "BornOn");
+        assert_eq!(relation_variant(""), "");
+    }
+}
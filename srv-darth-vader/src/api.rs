@@ -1,21 +1,44 @@
 use crate::{
-    models::{CharacterRaw, MoviesRaw, PlanetRaw, SpeciesRaw, StarshipRaw, VehicleRaw},
-    services::process_collection,
+    graph_audit::dangling_references,
+    ingest_all::{ALL_COLLECTIONS, plan_ingest_all, run_ingest_all},
+    jobs::{IngestJob, StopRequest},
+    reconcile::{ReconcileMode, ReconcileOptions},
+    retrieval::{
+        ContextRequest, SearchRequest, assemble_context, validate_labels, validate_relations,
+        vector_search,
+    },
+    schema::bootstrap_schema,
+    services::{decoder_for, run_ingest_job},
     state::AppState,
+    watcher::{start_watcher, stop_watcher, watched_collections},
 };
 use axum::{
     Json, Router,
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     response::IntoResponse,
-    routing::{get, post},
+    routing::{delete, get, post},
 };
+use serde::Deserialize;
 use std::sync::Arc;
 
 pub fn app_router(state: Arc<AppState>) -> Router {
     Router::new()
         .route("/health", get(health_check))
+        .route("/ingest", post(ingest_all_handler))
         .route("/ingest/{collection}", post(ingest_handler))
+        .route("/jobs", get(list_jobs_handler))
+        .route("/jobs/{id}", get(get_job_handler))
+        .route("/jobs/{id}/resume", post(resume_job_handler))
+        .route("/jobs/{id}/cancel", post(cancel_job_handler))
+        .route("/jobs/{id}/pause", post(pause_job_handler))
+        .route("/graph/schema", post(schema_handler))
+        .route("/graph/dangling", get(dangling_handler))
+        .route("/watchers", get(list_watchers_handler))
+        .route("/watchers/{collection}", post(start_watcher_handler))
+        .route("/watchers/{collection}", delete(stop_watcher_handler))
+        .route("/search", post(search_handler))
+        .route("/context", post(context_handler))
         .with_state(state)
 }
 
@@ -23,35 +46,521 @@ async fn health_check() -> impl IntoResponse {
     Json(serde_json::json!({ "status": "ok", "service": "srv-darth-vader" }))
 }
 
+#[derive(Debug, Deserialize)]
+struct IngestParams {
+    // archive | delete: al terminar, quita del grafo lo que ya no está en Mongo
+    reconcile: Option<ReconcileMode>,
+    #[serde(default)]
+    dry_run: bool,
+    // Si la colección ya se está ingestando: true devuelve ese job, si no 409
+    #[serde(default)]
+    attach: bool,
+}
+
 async fn ingest_handler(
     Path(collection): Path<String>,
+    Query(params): Query<IngestParams>,
     State(state): State<Arc<AppState>>,
 ) -> impl IntoResponse {
-    let col_clone = collection.clone();
-    let state_clone = state.clone();
+    let mut job = IngestJob::new(&collection);
+    job.reconcile = params.reconcile.map(|mode| ReconcileOptions {
+        mode,
+        dry_run: params.dry_run,
+    });
+    if job.reconcile.is_some()
+        && let Some(response) = reject_watched(&state, std::slice::from_ref(&collection)).await
+    {
+        return response;
+    }
 
-    // Background Task
-    tokio::spawn(async move {
-        let result = match col_clone.as_str() {
-            "characters_raw" => process_collection::<CharacterRaw>(&col_clone, state_clone).await,
-            "movies_raw" => process_collection::<MoviesRaw>(&col_clone, state_clone).await,
-            "planets_raw" => process_collection::<PlanetRaw>(&col_clone, state_clone).await,
-            "species_raw" => process_collection::<SpeciesRaw>(&col_clone, state_clone).await,
-            "starships_raw" => process_collection::<StarshipRaw>(&col_clone, state_clone).await,
-            "vehicles_raw" => process_collection::<VehicleRaw>(&col_clone, state_clone).await,
-            _ => Err(anyhow::anyhow!("Colección no mapeada")),
-        };
-
-        if let Err(e) = result {
-            eprintln!("Job failed for {}: {:?}", col_clone, e);
+    match state.job_registry.claim(&collection, &job.id).await {
+        Ok(None) => {}
+        Ok(Some(existing)) if params.attach => {
+            return (
+                StatusCode::OK,
+                Json(serde_json::json!({
+                    "status": "attached",
+                    "job_id": existing,
+                    "message": format!("Ya hay una ingesta en curso para {}", collection)
+                })),
+            );
         }
-    });
+        Ok(Some(existing)) => return collection_busy(&collection, &existing),
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "status": "error",
+                    "message": format!("No se pudo tomar el lease de {}: {}", collection, e)
+                })),
+            );
+        }
+    }
+
+    if let Err(e) = state.jobs.save(&job).await {
+        // Sin job registrado el lease quedaría tomado hasta vencer
+        let _ = state.job_registry.release(&job.collection, &job.id).await;
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "status": "error",
+                "message": format!("No se pudo registrar el job: {}", e)
+            })),
+        );
+    }
+
+    let job_id = job.id.clone();
+
+    // Background Task
+    tokio::spawn(run_ingest_job(state.clone(), job));
 
     (
         StatusCode::ACCEPTED,
         Json(serde_json::json!({
             "status": "pending",
+            "job_id": job_id,
             "message": format!("Ingesta iniciada para {}", collection)
         })),
     )
 }
+
+// Todas las colecciones en orden de dependencias: un job padre con un hijo por colección
+async fn ingest_all_handler(
+    Query(params): Query<IngestParams>,
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    let reconcile = params.reconcile.map(|mode| ReconcileOptions {
+        mode,
+        dry_run: params.dry_run,
+    });
+    let (parent, children) = plan_ingest_all(&state, reconcile);
+    if reconcile.is_some() {
+        let collections: Vec<String> = children.iter().map(|c| c.collection.clone()).collect();
+        if let Some(response) = reject_watched(&state, &collections).await {
+            return response;
+        }
+    }
+
+    match state.job_registry.claim(ALL_COLLECTIONS, &parent.id).await {
+        Ok(None) => {}
+        Ok(Some(existing)) if params.attach => {
+            return (
+                StatusCode::OK,
+                Json(serde_json::json!({
+                    "status": "attached",
+                    "job_id": existing,
+                    "message": "Ya hay una ingesta completa en curso"
+                })),
+            );
+        }
+        Ok(Some(existing)) => return collection_busy(ALL_COLLECTIONS, &existing),
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "status": "error",
+                    "message": format!("No se pudo tomar el lease de la ingesta completa: {}", e)
+                })),
+            );
+        }
+    }
+
+    for job in children.iter().chain(std::iter::once(&parent)) {
+        if let Err(e) = state.jobs.save(job).await {
+            let _ = state
+                .job_registry
+                .release(ALL_COLLECTIONS, &parent.id)
+                .await;
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "status": "error",
+                    "message": format!("No se pudo registrar el job: {}", e)
+                })),
+            );
+        }
+    }
+
+    let job_id = parent.id.clone();
+    let plan = parent.children.clone();
+    tokio::spawn(run_ingest_all(state.clone(), parent));
+
+    (
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({
+            "status": "pending",
+            "job_id": job_id,
+            "children": plan,
+            "message": "Ingesta completa iniciada"
+        })),
+    )
+}
+
+// La reconciliación no convive con un watcher activo en la misma colección
+async fn reject_watched(
+    state: &AppState,
+    collections: &[String],
+) -> Option<(StatusCode, Json<serde_json::Value>)> {
+    match watched_collections(state, collections).await {
+        Ok(watched) if watched.is_empty() => None,
+        Ok(watched) => Some((
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({
+                "status": "error",
+                "message": format!(
+                    "No se puede reconciliar con un watcher activo: {}",
+                    watched.join(", ")
+                )
+            })),
+        )),
+        Err(e) => Some((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "status": "error", "message": e.to_string() })),
+        )),
+    }
+}
+
+fn collection_busy(collection: &str, job_id: &str) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        StatusCode::CONFLICT,
+        Json(serde_json::json!({
+            "status": "error",
+            "job_id": job_id,
+            "message": format!("Ya hay una ingesta en curso para {} (job {})", collection, job_id)
+        })),
+    )
+}
+
+#[derive(Debug, Deserialize)]
+struct ListJobsParams {
+    collection: Option<String>,
+    limit: Option<i64>,
+}
+
+async fn list_jobs_handler(
+    Query(params): Query<ListJobsParams>,
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    let limit = params.limit.unwrap_or(50).clamp(1, 500);
+
+    match state.jobs.list(params.collection.as_deref(), limit).await {
+        Ok(jobs) => (StatusCode::OK, Json(serde_json::json!({ "jobs": jobs }))),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "status": "error", "message": e.to_string() })),
+        ),
+    }
+}
+
+async fn get_job_handler(
+    Path(id): Path<String>,
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    match state.jobs.get(&id).await {
+        Ok(Some(job)) => (StatusCode::OK, Json(serde_json::json!(job))),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "status": "error",
+                "message": format!("Job {} no encontrado", id)
+            })),
+        ),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "status": "error", "message": e.to_string() })),
+        ),
+    }
+}
+
+// Retoma un job interrumpido (mismo id) desde su último checkpoint
+async fn resume_job_handler(
+    Path(id): Path<String>,
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    let mut job = match state.jobs.get(&id).await {
+        Ok(Some(job)) => job,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({
+                    "status": "error",
+                    "message": format!("Job {} no encontrado", id)
+                })),
+            );
+        }
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "status": "error", "message": e.to_string() })),
+            );
+        }
+    };
+
+    if state.job_registry.is_running(&id) {
+        return (
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({
+                "status": "error",
+                "message": format!("El job {} sigue en ejecución", id)
+            })),
+        );
+    }
+    if job.is_finished() {
+        return (
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({
+                "status": "error",
+                "message": format!("El job {} ya terminó", id)
+            })),
+        );
+    }
+
+    match state.job_registry.claim(&job.collection, &job.id).await {
+        Ok(None) => {}
+        Ok(Some(existing)) => return collection_busy(&job.collection, &existing),
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "status": "error", "message": e.to_string() })),
+            );
+        }
+    }
+
+    job.resume();
+    if let Err(e) = state.jobs.save(&job).await {
+        // Sin job registrado el lease quedaría tomado hasta vencer
+        let _ = state.job_registry.release(&job.collection, &job.id).await;
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({
+                "status": "error",
+                "message": format!("No se pudo registrar el job: {}", e)
+            })),
+        );
+    }
+
+    let from = job.checkpoint.as_ref().map(|c| c.last_id.to_string());
+    if job.collection == ALL_COLLECTIONS {
+        tokio::spawn(run_ingest_all(state.clone(), job));
+    } else {
+        tokio::spawn(run_ingest_job(state.clone(), job));
+    }
+
+    (
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({
+            "status": "pending",
+            "job_id": id,
+            "resumed_from": from
+        })),
+    )
+}
+
+async fn cancel_job_handler(
+    Path(id): Path<String>,
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    stop_job(&state, &id, StopRequest::Cancel)
+}
+
+// El job se detiene con checkpoint; sigue con POST /jobs/{id}/resume
+async fn pause_job_handler(
+    Path(id): Path<String>,
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    stop_job(&state, &id, StopRequest::Pause)
+}
+
+// El loop de ingesta lo ve en el próximo documento y guarda el estado final
+fn stop_job(
+    state: &AppState,
+    id: &str,
+    stop: StopRequest,
+) -> (StatusCode, Json<serde_json::Value>) {
+    match state.job_registry.request_stop(id, stop) {
+        Some(requested) => (
+            StatusCode::ACCEPTED,
+            Json(serde_json::json!({
+                "status": "stopping",
+                "job_id": id,
+                "request": requested
+            })),
+        ),
+        None => (
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({
+                "status": "error",
+                "message": format!("El job {} no está en ejecución", id)
+            })),
+        ),
+    }
+}
+
+async fn schema_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let report = bootstrap_schema(
+        &state.graph,
+        &state.mappings.labels,
+        state.embedder.dimension(),
+        &state.ingest.vector_similarity,
+    )
+    .await;
+
+    // El reporte va igual: dice qué quedó creado y qué falló por label
+    let status = if report.failures.is_empty() {
+        StatusCode::OK
+    } else {
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+    (status, Json(serde_json::json!(report)))
+}
+
+#[derive(Debug, Deserialize)]
+struct DanglingParams {
+    sample: Option<i64>,
+}
+
+async fn dangling_handler(
+    Query(params): Query<DanglingParams>,
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    let sample = params.sample.unwrap_or(20).clamp(0, 500);
+
+    match dangling_references(&state.graph, sample).await {
+        Ok(report) => (StatusCode::OK, Json(serde_json::json!(report))),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "status": "error", "message": e.to_string() })),
+        ),
+    }
+}
+
+async fn list_watchers_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    match state.watchers.statuses().await {
+        Ok(watchers) => (
+            StatusCode::OK,
+            Json(serde_json::json!({ "watchers": watchers })),
+        ),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "status": "error", "message": e.to_string() })),
+        ),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct WatchParams {
+    // Qué hacer en el grafo cuando se borra un documento (archive por defecto)
+    on_delete: Option<ReconcileMode>,
+}
+
+async fn start_watcher_handler(
+    Path(collection): Path<String>,
+    Query(params): Query<WatchParams>,
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    if decoder_for(&state, &collection).is_none() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "status": "error",
+                "message": format!("Colección no mapeada: {}", collection)
+            })),
+        );
+    }
+
+    let on_delete = params.on_delete.unwrap_or(ReconcileMode::Archive);
+    match start_watcher(state.clone(), &collection, on_delete).await {
+        Ok(started) => (
+            StatusCode::OK,
+            Json(serde_json::json!({
+                "status": "watching",
+                "collection": collection,
+                "already_running": !started
+            })),
+        ),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "status": "error", "message": e.to_string() })),
+        ),
+    }
+}
+
+async fn stop_watcher_handler(
+    Path(collection): Path<String>,
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    match stop_watcher(&state, &collection).await {
+        Ok(was_running) => (
+            StatusCode::OK,
+            Json(serde_json::json!({
+                "status": "stopped",
+                "collection": collection,
+                "was_running": was_running
+            })),
+        ),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "status": "error", "message": e.to_string() })),
+        ),
+    }
+}
+
+async fn search_handler(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<SearchRequest>,
+) -> impl IntoResponse {
+    if request.query.trim().is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "status": "error", "message": "query vacío" })),
+        );
+    }
+    if let Err(e) = validate_labels(&state.mappings.labels, &request.labels) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "status": "error", "message": e.to_string() })),
+        );
+    }
+
+    match vector_search(&state, &request).await {
+        Ok(hits) => (
+            StatusCode::OK,
+            Json(serde_json::json!({
+                "model": state.embedder.model_name(),
+                "results": hits
+            })),
+        ),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "status": "error", "message": format!("{:#}", e) })),
+        ),
+    }
+}
+
+async fn context_handler(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<ContextRequest>,
+) -> impl IntoResponse {
+    if request.query.trim().is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "status": "error", "message": "query vacío" })),
+        );
+    }
+    if let Err(e) = validate_labels(&state.mappings.labels, &request.labels)
+        .and_then(|_| validate_relations(&request.relations))
+    {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "status": "error", "message": e.to_string() })),
+        );
+    }
+
+    match assemble_context(&state, &request).await {
+        Ok(context) => (StatusCode::OK, Json(serde_json::json!(context))),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "status": "error", "message": format!("{:#}", e) })),
+        ),
+    }
+}
@@ -0,0 +1,204 @@
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkUnit {
+    Chars,
+    // Aproximación: palabras separadas por espacios
+    Tokens,
+}
+
+impl FromStr for ChunkUnit {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "chars" | "char" => Ok(Self::Chars),
+            "tokens" | "token" | "words" => Ok(Self::Tokens),
+            other => Err(anyhow::anyhow!("CHUNK_UNIT desconocida: {}", other)),
+        }
+    }
+}
+
+// Ventana deslizante por oraciones: junta oraciones hasta `size` y arrastra
+// las últimas (hasta `overlap`) al siguiente chunk.
+#[derive(Debug, Clone)]
+pub struct Chunker {
+    pub size: usize,
+    pub overlap: usize,
+    pub unit: ChunkUnit,
+}
+
+impl Chunker {
+    pub fn new(size: usize, overlap: usize, unit: ChunkUnit) -> Self {
+        let size = size.max(1);
+        Self {
+            size,
+            overlap: overlap.min(size / 2),
+            unit,
+        }
+    }
+
+    pub fn chunk(&self, text: &str) -> Vec<String> {
+        let mut pieces = Vec::new();
+        for sentence in split_sentences(text) {
+            if self.measure(sentence) > self.size {
+                pieces.extend(self.hard_split(sentence));
+            } else {
+                pieces.push(sentence.to_string());
+            }
+        }
+
+        let mut chunks = Vec::new();
+        let mut window: Vec<String> = Vec::new();
+        let mut window_len = 0;
+
+        for piece in pieces {
+            let piece_len = self.measure(&piece);
+            if !window.is_empty() && window_len + piece_len > self.size {
+                chunks.push(window.join(" "));
+
+                // Solapamiento: conservar oraciones finales mientras quepan
+                let mut kept = Vec::new();
+                let mut kept_len = 0;
+                for prev in window.iter().rev() {
+                    let prev_len = self.measure(prev);
+                    if kept_len + prev_len > self.overlap
+                        || kept_len + prev_len + piece_len > self.size
+                    {
+                        break;
+                    }
+                    kept_len += prev_len;
+                    kept.push(prev.clone());
+                }
+                kept.reverse();
+                window = kept;
+                window_len = kept_len;
+            }
+            window_len += piece_len;
+            window.push(piece);
+        }
+
+        if !window.is_empty() {
+            chunks.push(window.join(" "));
+        }
+        chunks
+    }
+
+    fn measure(&self, text: &str) -> usize {
+        match self.unit {
+            ChunkUnit::Chars => text.chars().count(),
+            ChunkUnit::Tokens => text.split_whitespace().count(),
+        }
+    }
+
+    // Oraciones más largas que la ventana se cortan por unidad, respetando el solapamiento
+    fn hard_split(&self, sentence: &str) -> Vec<String> {
+        let units: Vec<&str> = match self.unit {
+            ChunkUnit::Chars => sentence
+                .char_indices()
+                .map(|(i, c)| &sentence[i..i + c.len_utf8()])
+                .collect(),
+            ChunkUnit::Tokens => sentence.split_whitespace().collect(),
+        };
+        let separator = match self.unit {
+            ChunkUnit::Chars => "",
+            ChunkUnit::Tokens => " ",
+        };
+
+        let step = (self.size - self.overlap).max(1);
+        let mut parts = Vec::new();
+        let mut start = 0;
+        while start < units.len() {
+            let end = (start + self.size).min(units.len());
+            parts.push(units[start..end].join(separator).trim().to_string());
+            if end == units.len() {
+                break;
+            }
+            start += step;
+        }
+        parts
+    }
+}
+
+fn split_sentences(text: &str) -> Vec<&str> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        let boundary = match c {
+            '\n' => true,
+            '.' | '!' | '?' => chars.peek().is_none_or(|(_, next)| next.is_whitespace()),
+            _ => false,
+        };
+        if boundary {
+            let end = i + c.len_utf8();
+            let sentence = text[start..end].trim();
+            if !sentence.is_empty() {
+                sentences.push(sentence);
+            }
+            start = end;
+        }
+    }
+
+    let tail = text[start..].trim();
+    if !tail.is_empty() {
+        sentences.push(tail);
+    }
+    sentences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_units() {
+        assert_eq!("Chars".parse::<ChunkUnit>().unwrap(), ChunkUnit::Chars);
+        assert_eq!(" words ".parse::<ChunkUnit>().unwrap(), ChunkUnit::Tokens);
+        assert!("bytes".parse::<ChunkUnit>().is_err());
+    }
+
+    #[test]
+    fn overlap_is_capped_to_half_the_window() {
+        let chunker = Chunker::new(20, 100, ChunkUnit::Chars);
+        assert_eq!(chunker.overlap, 10);
+        assert_eq!(Chunker::new(0, 0, ChunkUnit::Chars).size, 1);
+    }
+
+    #[test]
+    fn short_text_is_one_chunk() {
+        let chunker = Chunker::new(100, 10, ChunkUnit::Chars);
+        assert_eq!(chunker.chunk("Hola.  Chau."), vec!["Hola. Chau."]);
+        assert!(chunker.chunk("  ").is_empty());
+    }
+
+    #[test]
+    fn window_carries_trailing_sentences() {
+        let chunker = Chunker::new(4, 2, ChunkUnit::Tokens);
+        assert_eq!(
+            chunker.chunk("a b. c d. e f."),
+            vec!["a b. c d.", "c d. e f."]
+        );
+    }
+
+    #[test]
+    fn long_sentence_is_hard_split() {
+        let chunker = Chunker::new(4, 0, ChunkUnit::Tokens);
+        assert_eq!(
+            chunker.chunk("uno dos tres cuatro cinco seis"),
+            vec!["uno dos tres cuatro", "cinco seis"]
+        );
+
+        let chunker = Chunker::new(4, 2, ChunkUnit::Chars);
+        assert_eq!(chunker.chunk("abcdef"), vec!["abcd", "cdef"]);
+    }
+
+    #[test]
+    fn decimals_do_not_end_sentences() {
+        assert_eq!(
+            split_sentences("Mide 3.5 metros. Fin\nOtra"),
+            vec!["Mide 3.5 metros.", "Fin", "Otra"]
+        );
+    }
+}
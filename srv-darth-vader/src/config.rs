@@ -0,0 +1,158 @@
+use crate::{
+    chunking::{ChunkUnit, Chunker},
+    models::ATTRIBUTE_LABELS,
+};
+use std::{env, str::FromStr};
+
+// Parámetros de ingesta, todos con valores por defecto razonables
+#[derive(Debug, Clone)]
+pub struct IngestConfig {
+    pub graph_batch_size: usize,
+    pub embed_batch_size: usize,
+    pub embed_concurrency: usize,
+    pub embedding_cache: bool,
+    pub chunking: bool,
+    pub chunk_size: usize,
+    pub chunk_overlap: usize,
+    pub chunk_unit: ChunkUnit,
+    pub vector_similarity: String,
+    pub mappings_path: String,
+    pub split_attributes: Vec<String>,
+    pub reconcile_edges: bool,
+    // Cada cuántos documentos se guarda el checkpoint (0 lo desactiva)
+    pub checkpoint_every: u64,
+    // Vigencia del lease de una colección si la réplica deja de renovarlo
+    pub lease_ttl_secs: u64,
+}
+
+impl IngestConfig {
+    pub fn from_env() -> Self {
+        Self {
+            graph_batch_size: env_or("GRAPH_BATCH_SIZE", 100).max(1),
+            embed_batch_size: env_or("EMBED_BATCH_SIZE", 50).max(1),
+            embed_concurrency: env_or("EMBED_CONCURRENCY", 4).max(1),
+            embedding_cache: env_or("EMBEDDING_CACHE", true),
+            chunking: env_or("CHUNKING_ENABLED", true),
+            chunk_size: env_or("CHUNK_SIZE", 800),
+            chunk_overlap: env_or("CHUNK_OVERLAP", 120),
+            chunk_unit: env_or("CHUNK_UNIT", ChunkUnit::Chars),
+            vector_similarity: vector_similarity_from_env(),
+            split_attributes: split_attributes_from_env(),
+            reconcile_edges: env_or("RECONCILE_EDGES", true),
+            checkpoint_every: env_or("CHECKPOINT_EVERY", 200),
+            lease_ttl_secs: env_or("INGEST_LEASE_TTL_SECS", 60),
+            mappings_path: env::var("MAPPINGS_PATH")
+                .unwrap_or_else(|_| "mappings.toml".to_string()),
+        }
+    }
+
+    pub fn chunker(&self) -> Option<Chunker> {
+        self.chunking
+            .then(|| Chunker::new(self.chunk_size, self.chunk_overlap, self.chunk_unit))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmbeddingProviderKind {
+    Gemini,
+    OpenAi,
+    Hashing,
+}
+
+impl FromStr for EmbeddingProviderKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "gemini" | "google" => Ok(Self::Gemini),
+            "openai" | "ollama" | "llamacpp" | "llama.cpp" => Ok(Self::OpenAi),
+            "hashing" | "local" | "offline" => Ok(Self::Hashing),
+            other => Err(anyhow::anyhow!("EMBEDDING_PROVIDER desconocido: {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EmbeddingConfig {
+    pub provider: EmbeddingProviderKind,
+    pub model: String,
+    pub dimension: usize,
+    pub base_url: String,
+    pub api_key: Option<String>,
+    pub max_retries: u32,
+    pub backoff_base_ms: u64,
+    pub backoff_max_ms: u64,
+    pub requests_per_minute: u32,
+    pub rate_burst: u32,
+}
+
+impl EmbeddingConfig {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let provider: EmbeddingProviderKind = env::var("EMBEDDING_PROVIDER")
+            .unwrap_or_else(|_| "gemini".to_string())
+            .parse()?;
+
+        let default_model = match provider {
+            EmbeddingProviderKind::Gemini => "text-embedding-004",
+            EmbeddingProviderKind::OpenAi => "nomic-embed-text",
+            EmbeddingProviderKind::Hashing => "hashing-v1",
+        };
+
+        // Para Gemini se sigue aceptando GOOGLE_API_KEY
+        let api_key = env::var("EMBEDDING_API_KEY")
+            .ok()
+            .or_else(|| match provider {
+                EmbeddingProviderKind::Gemini => env::var("GOOGLE_API_KEY").ok(),
+                _ => None,
+            })
+            .filter(|k| !k.trim().is_empty());
+
+        Ok(Self {
+            provider,
+            model: env::var("EMBEDDING_MODEL").unwrap_or_else(|_| default_model.to_string()),
+            dimension: env_or("EMBEDDING_DIMENSION", 768),
+            base_url: env::var("EMBEDDING_BASE_URL")
+                .unwrap_or_else(|_| "http://localhost:11434".to_string()),
+            api_key,
+            max_retries: env_or("EMBED_MAX_RETRIES", 5),
+            backoff_base_ms: env_or("EMBED_BACKOFF_BASE_MS", 500),
+            backoff_max_ms: env_or("EMBED_BACKOFF_MAX_MS", 30_000),
+            // 0 desactiva el rate limit del lado cliente
+            requests_per_minute: env_or("EMBED_REQUESTS_PER_MINUTE", 0),
+            rate_burst: env_or("EMBED_RATE_BURST", 5),
+        })
+    }
+}
+
+// Neo4j solo acepta cosine o euclidean en índices vectoriales
+fn vector_similarity_from_env() -> String {
+    match env::var("EMBEDDING_SIMILARITY")
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase()
+        .as_str()
+    {
+        "euclidean" => "euclidean".to_string(),
+        _ => "cosine".to_string(),
+    }
+}
+
+// Lista separada por comas de labels de atributos; "none" desactiva la extracción
+fn split_attributes_from_env() -> Vec<String> {
+    match env::var("SPLIT_ATTRIBUTES") {
+        Ok(value) if value.trim().eq_ignore_ascii_case("none") => Vec::new(),
+        Ok(value) if !value.trim().is_empty() => value
+            .split(',')
+            .map(|label| label.trim().to_string())
+            .filter(|label| !label.is_empty())
+            .collect(),
+        _ => ATTRIBUTE_LABELS.iter().map(|l| l.to_string()).collect(),
+    }
+}
+
+fn env_or<T: FromStr>(key: &str, default: T) -> T {
+    env::var(key)
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(default)
+}
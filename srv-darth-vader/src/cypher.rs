@@ -0,0 +1,105 @@
+// Helpers para los identificadores que se interpolan en Cypher (labels, tipos de
+// relación, claves de propiedad). Los valores siempre van como parámetros.
+
+const MAX_IDENTIFIER_LEN: usize = 64;
+
+// Solo ASCII: letra inicial y luego letras, dígitos o '_'
+pub fn is_valid_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    let Some(first) = chars.next() else {
+        return false;
+    };
+    name.len() <= MAX_IDENTIFIER_LEN
+        && first.is_ascii_alphabetic()
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// Backticks como segunda barrera, aunque el identificador ya venga validado
+pub fn quote_identifier(name: &str) -> String {
+    format!("`{}`", name.replace('`', "``"))
+}
+
+// Las claves de metadata se normalizan a identificadores válidos en vez de descartarse
+pub fn escape_property_key(key: &str) -> Option<String> {
+    let mut escaped: String = key
+        .trim()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .take(MAX_IDENTIFIER_LEN)
+        .collect();
+
+    if escaped.chars().all(|c| c == '_') {
+        return None;
+    }
+    if !escaped.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        escaped.insert(0, 'p');
+        escaped.truncate(MAX_IDENTIFIER_LEN);
+    }
+    Some(escaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_valid_identifier_only_accepts_ascii_identifiers() {
+        assert!(is_valid_identifier("Character"));
+        assert!(is_valid_identifier("HAS_SKIN_COLOR"));
+        assert!(is_valid_identifier("a1"));
+        assert!(is_valid_identifier(&"a".repeat(MAX_IDENTIFIER_LEN)));
+
+        assert!(!is_valid_identifier(""));
+        assert!(!is_valid_identifier("1Character"));
+        assert!(!is_valid_identifier("_Character"));
+        assert!(!is_valid_identifier("Char`acter"));
+        assert!(!is_valid_identifier("`Character`"));
+        assert!(!is_valid_identifier("Char acter"));
+        assert!(!is_valid_identifier(" Character"));
+        assert!(!is_valid_identifier("Character\n"));
+        assert!(!is_valid_identifier("Personaje_ñ"));
+        assert!(!is_valid_identifier("Ñandú"));
+        assert!(!is_valid_identifier(&"a".repeat(MAX_IDENTIFIER_LEN + 1)));
+    }
+
+    #[test]
+    fn quote_identifier_doubles_backticks() {
+        assert_eq!(quote_identifier("Character"), "`Character`");
+        assert_eq!(quote_identifier("a`b"), "`a``b`");
+        assert_eq!(quote_identifier(""), "``");
+    }
+
+    #[test]
+    fn escape_property_key_normalizes_to_identifiers() {
+        assert_eq!(escape_property_key("model").as_deref(), Some("model"));
+        assert_eq!(escape_property_key("  model  ").as_deref(), Some("model"));
+        assert_eq!(
+            escape_property_key("max speed").as_deref(),
+            Some("max_speed")
+        );
+        assert_eq!(escape_property_key("a`b").as_deref(), Some("a_b"));
+        assert_eq!(escape_property_key("año").as_deref(), Some("a_o"));
+        // Dígito o '_' inicial: se antepone una letra
+        assert_eq!(escape_property_key("2nd").as_deref(), Some("p2nd"));
+        assert_eq!(escape_property_key("_id").as_deref(), Some("p_id"));
+
+        assert_eq!(escape_property_key(""), None);
+        assert_eq!(escape_property_key("   "), None);
+        assert_eq!(escape_property_key("``"), None);
+        assert_eq!(escape_property_key("ñ"), None);
+    }
+
+    #[test]
+    fn escape_property_key_respects_max_length() {
+        let long = "a".repeat(MAX_IDENTIFIER_LEN * 2);
+        assert_eq!(
+            escape_property_key(&long).map(|k| k.len()),
+            Some(MAX_IDENTIFIER_LEN)
+        );
+
+        let long_digits = "1".repeat(MAX_IDENTIFIER_LEN);
+        let escaped = escape_property_key(&long_digits).unwrap();
+        assert_eq!(escaped.len(), MAX_IDENTIFIER_LEN);
+        assert!(is_valid_identifier(&escaped));
+    }
+}
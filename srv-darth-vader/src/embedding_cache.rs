@@ -0,0 +1,126 @@
+use crate::utils::now_millis;
+use anyhow::Result;
+use futures::stream::TryStreamExt;
+use mongodb::{Collection, Database, bson::doc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+pub const CACHE_COLLECTION: &str = "embedding_cache";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedEmbedding {
+    #[serde(rename = "_id")]
+    key: String,
+    model: String,
+    vector: Vec<f32>,
+    created_at: i64,
+}
+
+// Cache de embeddings por hash(modelo + dimensión + texto): solo lo nuevo o
+// modificado llega al proveedor. Cambiar EMBEDDING_DIMENSION invalida la cache.
+#[derive(Clone)]
+pub struct EmbeddingCache {
+    collection: Collection<CachedEmbedding>,
+}
+
+impl EmbeddingCache {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection::<CachedEmbedding>(CACHE_COLLECTION),
+        }
+    }
+
+    pub fn key(model: &str, dimension: usize, text: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(model.as_bytes());
+        hasher.update([0u8]);
+        hasher.update(dimension.to_string().as_bytes());
+        hasher.update([0u8]);
+        hasher.update(text.as_bytes());
+        format!("{:x}", hasher.finalize())
+    }
+
+    // Un vector de otro largo no sirve para el índice: se borra y cuenta como miss
+    pub async fn get_many(
+        &self,
+        keys: &[String],
+        dimension: usize,
+    ) -> Result<HashMap<String, Vec<f32>>> {
+        if keys.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let cursor = self
+            .collection
+            .find(doc! { "_id": { "$in": keys } })
+            .await?;
+        let hits: Vec<CachedEmbedding> = cursor.try_collect().await?;
+
+        let (hits, stale): (Vec<_>, Vec<_>) = hits
+            .into_iter()
+            .partition(|hit| hit.vector.len() == dimension);
+        if !stale.is_empty() {
+            let stale: Vec<String> = stale.into_iter().map(|hit| hit.key).collect();
+            eprintln!(
+                "⚠️ {} embeddings en cache con dimensión distinta de {}: se recalculan",
+                stale.len(),
+                dimension
+            );
+            self.collection
+                .delete_many(doc! { "_id": { "$in": stale } })
+                .await?;
+        }
+
+        Ok(hits.into_iter().map(|hit| (hit.key, hit.vector)).collect())
+    }
+
+    pub async fn put_many(&self, model: &str, entries: Vec<(String, Vec<f32>)>) -> Result<()> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+
+        let created_at = now_millis();
+        let docs: Vec<CachedEmbedding> = entries
+            .into_iter()
+            .map(|(key, vector)| CachedEmbedding {
+                key,
+                model: model.to_string(),
+                vector,
+                created_at,
+            })
+            .collect();
+
+        // Desordenado: si otro job ya guardó la misma clave, el duplicado no corta el resto
+        if let Err(e) = self.collection.insert_many(docs).ordered(false).await
+            && !is_duplicate_key(&e)
+        {
+            return Err(e.into());
+        }
+        Ok(())
+    }
+}
+
+fn is_duplicate_key(err: &mongodb::error::Error) -> bool {
+    match err.kind.as_ref() {
+        mongodb::error::ErrorKind::InsertMany(e) => e
+            .write_errors
+            .as_ref()
+            .is_some_and(|errors| errors.iter().all(|w| w.code == 11000)),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_depends_on_model_dimension_and_text() {
+        let key = EmbeddingCache::key("hashing-v1", 384, "Luke");
+        assert_eq!(key, EmbeddingCache::key("hashing-v1", 384, "Luke"));
+        assert_ne!(key, EmbeddingCache::key("hashing-v1", 768, "Luke"));
+        assert_ne!(key, EmbeddingCache::key("otro", 384, "Luke"));
+        assert_ne!(key, EmbeddingCache::key("hashing-v1", 384, "Leia"));
+    }
+}
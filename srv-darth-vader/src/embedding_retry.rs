@@ -0,0 +1,230 @@
+use crate::{
+    config::EmbeddingConfig,
+    embeddings::{ApiError, EmbeddingProvider},
+};
+use anyhow::Result;
+use async_trait::async_trait;
+use rand::Rng;
+use std::{
+    future::Future,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+// Decora un proveedor con rate limit (token bucket) y reintentos con backoff exponencial
+pub struct RetryingEmbedder {
+    inner: Arc<dyn EmbeddingProvider>,
+    limiter: Option<TokenBucket>,
+    max_retries: u32,
+    backoff_base: Duration,
+    backoff_max: Duration,
+}
+
+impl RetryingEmbedder {
+    pub fn new(inner: Arc<dyn EmbeddingProvider>, config: &EmbeddingConfig) -> Self {
+        let limiter = (config.requests_per_minute > 0)
+            .then(|| TokenBucket::per_minute(config.requests_per_minute, config.rate_burst));
+
+        Self {
+            inner,
+            limiter,
+            max_retries: config.max_retries,
+            backoff_base: Duration::from_millis(config.backoff_base_ms),
+            backoff_max: Duration::from_millis(config.backoff_max_ms),
+        }
+    }
+
+    async fn with_retry<F, Fut, R>(&self, mut op: F) -> Result<R>
+    where
+        F: FnMut() -> Fut + Send,
+        Fut: Future<Output = Result<R>> + Send,
+        R: Send,
+    {
+        let mut attempt = 0;
+
+        loop {
+            if let Some(limiter) = &self.limiter {
+                limiter.acquire().await;
+            }
+
+            let err = match op().await {
+                Ok(value) => return Ok(value),
+                Err(e) => e,
+            };
+
+            let Some(retry_after) = retry_hint(&err) else {
+                return Err(err);
+            };
+            if attempt >= self.max_retries {
+                return Err(err.context(format!("Sin éxito tras {} reintentos", attempt)));
+            }
+
+            // Un Retry-After más largo que el tope no se espera: el job no queda colgado
+            let retry_after = retry_after.unwrap_or_default();
+            if retry_after > self.backoff_max {
+                return Err(err.context(format!(
+                    "Retry-After de {:?} supera el máximo de {:?}",
+                    retry_after, self.backoff_max
+                )));
+            }
+
+            let delay = self.backoff(attempt).max(retry_after);
+            attempt += 1;
+            eprintln!(
+                "⏳ Reintento {}/{} de embedding en {:?}: {}",
+                attempt, self.max_retries, delay, err
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    // Full jitter: aleatorio entre 0 y base * 2^intento, con tope
+    fn backoff(&self, attempt: u32) -> Duration {
+        let ceiling = self
+            .backoff_base
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.backoff_max);
+        let jitter_ms = rand::rng().random_range(0..=ceiling.as_millis() as u64);
+        Duration::from_millis(jitter_ms)
+    }
+}
+
+// `None` si el error no merece reintento; `Some(retry_after)` si sí
+fn retry_hint(err: &anyhow::Error) -> Option<Option<Duration>> {
+    if let Some(api) = err.downcast_ref::<ApiError>() {
+        return api.is_retryable().then_some(api.retry_after);
+    }
+    if let Some(http) = err.downcast_ref::<reqwest::Error>() {
+        return (http.is_timeout() || http.is_connect() || http.is_request()).then_some(None);
+    }
+    None
+}
+
+#[async_trait]
+impl EmbeddingProvider for RetryingEmbedder {
+    fn model_name(&self) -> &str {
+        self.inner.model_name()
+    }
+
+    fn dimension(&self) -> usize {
+        self.inner.dimension()
+    }
+
+    fn max_batch_size(&self) -> usize {
+        self.inner.max_batch_size()
+    }
+
+    async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        self.with_retry(|| self.inner.embed(text)).await
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        self.with_retry(|| self.inner.embed_batch(texts)).await
+    }
+}
+
+pub struct TokenBucket {
+    capacity: f64,
+    refill_per_sec: f64,
+    state: Mutex<(f64, Instant)>,
+}
+
+impl TokenBucket {
+    pub fn per_minute(requests_per_minute: u32, burst: u32) -> Self {
+        let capacity = burst.max(1) as f64;
+        Self {
+            capacity,
+            refill_per_sec: requests_per_minute as f64 / 60.0,
+            state: Mutex::new((capacity, Instant::now())),
+        }
+    }
+
+    pub async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+                let (tokens, last) = &mut *state;
+
+                let now = Instant::now();
+                *tokens = (*tokens + now.duration_since(*last).as_secs_f64() * self.refill_per_sec)
+                    .min(self.capacity);
+                *last = now;
+
+                if *tokens >= 1.0 {
+                    *tokens -= 1.0;
+                    return;
+                }
+                Duration::from_secs_f64((1.0 - *tokens) / self.refill_per_sec)
+            };
+
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    // Responde siempre 429 con el Retry-After dado
+    struct RateLimited {
+        retry_after: Duration,
+        calls: AtomicU32,
+    }
+
+    #[async_trait]
+    impl EmbeddingProvider for RateLimited {
+        fn model_name(&self) -> &str {
+            "test"
+        }
+
+        fn dimension(&self) -> usize {
+            1
+        }
+
+        async fn embed(&self, _text: &str) -> Result<Vec<f32>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Err(ApiError {
+                provider: "test",
+                status: 429,
+                retry_after: Some(self.retry_after),
+                body: String::new(),
+            }
+            .into())
+        }
+    }
+
+    fn embedder(retry_after: Duration) -> (Arc<RateLimited>, RetryingEmbedder) {
+        let inner = Arc::new(RateLimited {
+            retry_after,
+            calls: AtomicU32::new(0),
+        });
+        let embedder = RetryingEmbedder {
+            inner: inner.clone(),
+            limiter: None,
+            max_retries: 3,
+            backoff_base: Duration::from_millis(1),
+            backoff_max: Duration::from_millis(20),
+        };
+        (inner, embedder)
+    }
+
+    #[tokio::test]
+    async fn retry_after_past_backoff_max_fails_without_waiting() {
+        let (inner, embedder) = embedder(Duration::from_secs(3600));
+        let started = Instant::now();
+
+        assert!(embedder.embed("hola").await.is_err());
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 1);
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn retry_after_within_backoff_max_is_retried() {
+        let (inner, embedder) = embedder(Duration::from_millis(5));
+
+        assert!(embedder.embed("hola").await.is_err());
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 4);
+    }
+}
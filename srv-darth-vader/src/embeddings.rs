@@ -0,0 +1,463 @@
+use crate::{
+    config::{EmbeddingConfig, EmbeddingProviderKind},
+    embedding_retry::RetryingEmbedder,
+};
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use reqwest::header::RETRY_AFTER;
+use serde_json::Value;
+use std::{fmt, sync::Arc, time::Duration};
+
+#[async_trait]
+pub trait EmbeddingProvider: Send + Sync {
+    fn model_name(&self) -> &str;
+    fn dimension(&self) -> usize;
+    async fn embed(&self, text: &str) -> Result<Vec<f32>>;
+
+    // Cuántos textos acepta una sola llamada a `embed_batch`
+    fn max_batch_size(&self) -> usize {
+        1
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let mut vectors = Vec::with_capacity(texts.len());
+        for text in texts {
+            vectors.push(self.embed(text).await?);
+        }
+        Ok(vectors)
+    }
+}
+
+pub fn provider_from_config(config: &EmbeddingConfig) -> Result<Arc<dyn EmbeddingProvider>> {
+    let provider: Arc<dyn EmbeddingProvider> = match config.provider {
+        EmbeddingProviderKind::Gemini => Arc::new(GeminiEmbedder::new(
+            config.api_key.clone().unwrap_or_default(),
+            config.model.clone(),
+            config.dimension,
+        )?),
+        EmbeddingProviderKind::OpenAi => Arc::new(OpenAiEmbedder::new(
+            config.base_url.clone(),
+            config.api_key.clone(),
+            config.model.clone(),
+            config.dimension,
+        )),
+        EmbeddingProviderKind::Hashing => Arc::new(HashingEmbedder::new(config.dimension)),
+    };
+
+    // El embedder local no hace llamadas de red, no necesita reintentos
+    let provider: Arc<dyn EmbeddingProvider> = match config.provider {
+        EmbeddingProviderKind::Hashing => provider,
+        _ => Arc::new(RetryingEmbedder::new(provider, config)),
+    };
+
+    println!(
+        "🧠 Embeddings: {:?} / {} ({} dims)",
+        config.provider,
+        provider.model_name(),
+        provider.dimension()
+    );
+    Ok(provider)
+}
+
+// Error HTTP del proveedor; conserva el status y `Retry-After` para decidir reintentos
+#[derive(Debug)]
+pub struct ApiError {
+    pub provider: &'static str,
+    pub status: u16,
+    pub retry_after: Option<Duration>,
+    pub body: String,
+}
+
+impl ApiError {
+    async fn from_response(provider: &'static str, res: reqwest::Response) -> Self {
+        let status = res.status().as_u16();
+        let retry_after = res
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| parse_retry_after(v, Utc::now()));
+        let body = res.text().await.unwrap_or_default();
+
+        Self {
+            provider,
+            status,
+            retry_after,
+            body,
+        }
+    }
+
+    pub fn is_retryable(&self) -> bool {
+        self.status == 429 || self.status >= 500
+    }
+}
+
+// `Retry-After` admite segundos ("120") o una fecha HTTP ("Wed, 21 Oct 2015 07:28:00 GMT")
+fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = DateTime::parse_from_rfc2822(value).ok()?;
+    // Una fecha ya pasada significa reintentar ya
+    Some(
+        (at.with_timezone(&Utc) - now)
+            .to_std()
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} API Error ({}): {}",
+            self.provider, self.status, self.body
+        )
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+fn check_input(text: &str) -> Result<()> {
+    if text.trim().is_empty() {
+        return Err(anyhow::anyhow!(
+            "Texto vacío, no se puede generar embedding"
+        ));
+    }
+    Ok(())
+}
+
+fn check_dimension(values: &[f32], expected: usize) -> Result<()> {
+    if values.len() != expected {
+        return Err(anyhow::anyhow!(
+            "Dimensión de embedding inesperada: {} (configurada {})",
+            values.len(),
+            expected
+        ));
+    }
+    Ok(())
+}
+
+fn check_batch(vectors: &[Vec<f32>], expected_len: usize, dimension: usize) -> Result<()> {
+    if vectors.len() != expected_len {
+        return Err(anyhow::anyhow!(
+            "Se esperaban {} embeddings y llegaron {}",
+            expected_len,
+            vectors.len()
+        ));
+    }
+    for values in vectors {
+        check_dimension(values, dimension)?;
+    }
+    Ok(())
+}
+
+fn parse_vector(values: &Value) -> Option<Vec<f32>> {
+    Some(
+        values
+            .as_array()?
+            .iter()
+            .map(|v| v.as_f64().unwrap_or(0.0) as f32)
+            .collect(),
+    )
+}
+
+// --- GEMINI ---
+pub struct GeminiEmbedder {
+    client: reqwest::Client,
+    key: String,
+    model: String,
+    dimension: usize,
+}
+
+impl GeminiEmbedder {
+    pub fn new(key: String, model: String, dimension: usize) -> Result<Self> {
+        if key.trim().is_empty() {
+            return Err(anyhow::anyhow!(
+                "CRÍTICO: La Google API Key está vacía. Revisa tu .env"
+            ));
+        }
+
+        Ok(Self {
+            client: reqwest::Client::new(),
+            key,
+            model,
+            dimension,
+        })
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for GeminiEmbedder {
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        check_input(text)?;
+
+        let url = format!(
+            "https://generativelanguage.googleapis.com/v1beta/models/{}:embedContent",
+            self.model
+        );
+
+        let res = self
+            .client
+            .post(url)
+            .header("x-goog-api-key", &self.key)
+            .header("Content-Type", "application/json")
+            .json(&serde_json::json!({
+                "content": {
+                    "parts": [{ "text": text }]
+                }
+            }))
+            .send()
+            .await?;
+
+        if !res.status().is_success() {
+            return Err(ApiError::from_response("Gemini", res).await.into());
+        }
+
+        let json: Value = res.json().await?;
+
+        let values = parse_vector(&json["embedding"]["values"])
+            .ok_or_else(|| anyhow::anyhow!("Formato respuesta Gemini inválido: {:?}", json))?;
+
+        check_dimension(&values, self.dimension)?;
+        Ok(values)
+    }
+
+    fn max_batch_size(&self) -> usize {
+        100
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        for text in texts {
+            check_input(text)?;
+        }
+
+        let url = format!(
+            "https://generativelanguage.googleapis.com/v1beta/models/{}:batchEmbedContents",
+            self.model
+        );
+
+        let requests: Vec<Value> = texts
+            .iter()
+            .map(|text| {
+                serde_json::json!({
+                    "model": format!("models/{}", self.model),
+                    "content": { "parts": [{ "text": text }] }
+                })
+            })
+            .collect();
+
+        let res = self
+            .client
+            .post(url)
+            .header("x-goog-api-key", &self.key)
+            .header("Content-Type", "application/json")
+            .json(&serde_json::json!({ "requests": requests }))
+            .send()
+            .await?;
+
+        if !res.status().is_success() {
+            return Err(ApiError::from_response("Gemini", res).await.into());
+        }
+
+        let json: Value = res.json().await?;
+
+        let vectors = json["embeddings"]
+            .as_array()
+            .and_then(|items| {
+                items
+                    .iter()
+                    .map(|item| parse_vector(&item["values"]))
+                    .collect::<Option<Vec<_>>>()
+            })
+            .ok_or_else(|| anyhow::anyhow!("Formato respuesta Gemini inválido: {:?}", json))?;
+
+        check_batch(&vectors, texts.len(), self.dimension)?;
+        Ok(vectors)
+    }
+}
+
+// --- OPENAI-COMPATIBLE (OpenAI, Ollama, llama.cpp, ...) ---
+pub struct OpenAiEmbedder {
+    client: reqwest::Client,
+    endpoint: String,
+    key: Option<String>,
+    model: String,
+    dimension: usize,
+}
+
+impl OpenAiEmbedder {
+    pub fn new(base_url: String, key: Option<String>, model: String, dimension: usize) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            endpoint: format!("{}/v1/embeddings", base_url.trim_end_matches('/')),
+            key,
+            model,
+            dimension,
+        }
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for OpenAiEmbedder {
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        check_input(text)?;
+
+        let mut vectors = self.request(serde_json::json!(text)).await?;
+        check_batch(&vectors, 1, self.dimension)?;
+        Ok(vectors.remove(0))
+    }
+
+    fn max_batch_size(&self) -> usize {
+        64
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        for text in texts {
+            check_input(text)?;
+        }
+
+        let vectors = self.request(serde_json::json!(texts)).await?;
+        check_batch(&vectors, texts.len(), self.dimension)?;
+        Ok(vectors)
+    }
+}
+
+impl OpenAiEmbedder {
+    // `input` puede ser un string o un array; la respuesta trae un `index` por texto
+    async fn request(&self, input: Value) -> Result<Vec<Vec<f32>>> {
+        let mut req = self.client.post(&self.endpoint).json(&serde_json::json!({
+            "model": self.model,
+            "input": input
+        }));
+        if let Some(key) = &self.key {
+            req = req.bearer_auth(key);
+        }
+
+        let res = req.send().await?;
+
+        if !res.status().is_success() {
+            return Err(ApiError::from_response("Embeddings", res).await.into());
+        }
+
+        let json: Value = res.json().await?;
+
+        let mut items = json["data"]
+            .as_array()
+            .and_then(|items| {
+                items
+                    .iter()
+                    .map(|item| {
+                        let index = item["index"].as_u64().unwrap_or(0);
+                        parse_vector(&item["embedding"]).map(|v| (index, v))
+                    })
+                    .collect::<Option<Vec<_>>>()
+            })
+            .ok_or_else(|| anyhow::anyhow!("Formato respuesta embeddings inválido: {:?}", json))?;
+
+        items.sort_by_key(|(index, _)| *index);
+        Ok(items.into_iter().map(|(_, v)| v).collect())
+    }
+}
+
+// --- HASHING (offline, determinista) ---
+// Feature hashing de tokens: no entiende semántica, pero sirve para dev y CI.
+pub struct HashingEmbedder {
+    dimension: usize,
+}
+
+impl HashingEmbedder {
+    pub fn new(dimension: usize) -> Self {
+        Self {
+            dimension: dimension.max(1),
+        }
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for HashingEmbedder {
+    fn model_name(&self) -> &str {
+        "hashing-v1"
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        check_input(text)?;
+
+        let mut values = vec![0.0f32; self.dimension];
+        let tokens = text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|t| !t.is_empty())
+            .map(str::to_lowercase);
+
+        for token in tokens {
+            let hash = fnv1a(token.as_bytes());
+            let index = (hash % self.dimension as u64) as usize;
+            let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+            values[index] += sign;
+        }
+
+        let norm = values.iter().map(|v| v * v).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            values.iter_mut().for_each(|v| *v /= norm);
+        }
+
+        Ok(values)
+    }
+
+    fn max_batch_size(&self) -> usize {
+        1024
+    }
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ *b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_after_accepts_seconds_and_http_dates() {
+        let now = DateTime::parse_from_rfc2822("Wed, 21 Oct 2015 07:28:00 GMT")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        assert_eq!(
+            parse_retry_after(" 120 ", now),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:29:30 GMT", now),
+            Some(Duration::from_secs(90))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:00:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("pronto", now), None);
+    }
+}
@@ -0,0 +1,64 @@
+use crate::models::ENTITY_LABEL;
+use anyhow::Result;
+use neo4rs::{Graph, query};
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+pub struct DanglingGroup {
+    pub label: Option<String>,
+    // Colección cuyas relaciones apuntan al placeholder (None si no hay procedencia)
+    pub referenced_by: Option<String>,
+    pub count: i64,
+    pub sample_ids: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DanglingReport {
+    pub total: i64,
+    pub groups: Vec<DanglingGroup>,
+}
+
+// Nodos que solo existen porque una relación los referencia (placeholder: true),
+// agrupados por label y por la colección que los referenció.
+pub async fn dangling_references(graph: &Graph, sample: i64) -> Result<DanglingReport> {
+    let mut rows = graph
+        .execute(
+            query(
+                "MATCH (n {placeholder: true})
+                 OPTIONAL MATCH (n)-[r]-()
+                 WITH n,
+                      head([l IN labels(n) WHERE l <> $entity_label]) AS label,
+                      collect(DISTINCT r.source_collection) AS collections
+                 UNWIND CASE WHEN size(collections) = 0 THEN [null] ELSE collections END AS referenced_by
+                 WITH label, referenced_by, collect(n.id) AS ids
+                 RETURN label, referenced_by, size(ids) AS count, ids[..$sample] AS sample_ids
+                 ORDER BY label, referenced_by",
+            )
+            .param("entity_label", ENTITY_LABEL)
+            .param("sample", sample),
+        )
+        .await?;
+
+    let mut groups = Vec::new();
+    while let Some(row) = rows.next().await? {
+        groups.push(DanglingGroup {
+            label: row.get("label").ok(),
+            referenced_by: row.get("referenced_by").ok(),
+            count: row.get("count")?,
+            sample_ids: row.get("sample_ids").unwrap_or_default(),
+        });
+    }
+
+    // Un placeholder referenciado desde dos colecciones aparece en ambos grupos
+    let mut count_rows = graph
+        .execute(query(
+            "MATCH (n {placeholder: true}) RETURN count(n) AS total",
+        ))
+        .await?;
+    let total = match count_rows.next().await? {
+        Some(row) => row.get("total")?,
+        None => 0,
+    };
+
+    Ok(DanglingReport { total, groups })
+}
@@ -0,0 +1,426 @@
+use crate::{
+    cypher::{escape_property_key, quote_identifier},
+    models::{CHUNK_LABEL, ENTITY_LABEL, GraphEdge, GraphableSource, Label, RelationType},
+    utils::json_map_to_bolt_type,
+};
+use anyhow::Result;
+use chrono::NaiveDate;
+use neo4rs::{BoltDate, BoltMap, BoltNull, BoltType, Graph, Neo4jErrorKind, Query, query};
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+    time::Duration,
+};
+
+pub enum NodeEmbedding {
+    Vector(Vec<f32>),
+    Failed(String),
+    NoText,
+}
+
+impl NodeEmbedding {
+    fn status(&self) -> &'static str {
+        match self {
+            NodeEmbedding::Vector(_) => "embedded",
+            NodeEmbedding::Failed(_) => "embedding_failed",
+            NodeEmbedding::NoText => "no_text",
+        }
+    }
+}
+
+pub struct ChunkEmbedding {
+    pub ordinal: i64,
+    pub text: String,
+    pub embedding: NodeEmbedding,
+}
+
+struct PendingEntity {
+    label: Label,
+    id: String,
+    name: String,
+    props: BoltType,
+    source_oid: Option<String>,
+    embedding: NodeEmbedding,
+    chunks: Vec<ChunkEmbedding>,
+    edges: Vec<GraphEdge>,
+}
+
+pub struct WriteOutcome {
+    pub name: String,
+    pub error: Option<String>,
+}
+
+// Acumula entidades y las escribe con un UNWIND por label / tipo de relación
+pub struct GraphBatchWriter {
+    graph: Arc<Graph>,
+    batch_size: usize,
+    // Colección de origen, se registra como procedencia de nodos y relaciones
+    collection: String,
+    // Corrida de ingesta (id del job) que tocó cada nodo, para la reconciliación
+    run_id: String,
+    reconcile_edges: bool,
+    labels_seen: BTreeSet<Label>,
+    pending: Vec<PendingEntity>,
+}
+
+impl GraphBatchWriter {
+    pub fn new(graph: Arc<Graph>, batch_size: usize, collection: &str, run_id: &str) -> Self {
+        Self {
+            graph,
+            batch_size: batch_size.max(1),
+            collection: collection.to_string(),
+            run_id: run_id.to_string(),
+            reconcile_edges: true,
+            labels_seen: BTreeSet::new(),
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn reconcile_edges(mut self, enabled: bool) -> Self {
+        self.reconcile_edges = enabled;
+        self
+    }
+
+    pub fn labels_seen(&self) -> Vec<Label> {
+        self.labels_seen.iter().cloned().collect()
+    }
+
+    pub fn push(
+        &mut self,
+        entity: &dyn GraphableSource,
+        embedding: NodeEmbedding,
+        chunks: Vec<ChunkEmbedding>,
+    ) {
+        let metadata = escape_property_keys(entity.get_metadata_as_map());
+        let source_oid = metadata
+            .get("original_oid")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+
+        let label = entity.get_entity_label();
+        self.labels_seen.insert(label.clone());
+
+        self.pending.push(PendingEntity {
+            label,
+            id: entity.get_entity_id(),
+            name: entity.get_entity_name(),
+            props: node_properties(metadata, entity.get_date_properties()),
+            source_oid,
+            embedding,
+            chunks,
+            edges: entity.get_edges(),
+        });
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.pending.len() >= self.batch_size
+    }
+
+    // Escribe el lote en una transacción. Si falla, reintenta entidad por entidad
+    // para poder reportar exactamente cuáles fallaron.
+    pub async fn flush(&mut self) -> Vec<WriteOutcome> {
+        let batch = std::mem::take(&mut self.pending);
+        if batch.is_empty() {
+            return Vec::new();
+        }
+
+        match self.write(&batch).await {
+            Ok(()) => batch
+                .into_iter()
+                .map(|entity| WriteOutcome {
+                    name: entity.name,
+                    error: None,
+                })
+                .collect(),
+            Err(e) => {
+                eprintln!(
+                    "⚠️ Lote de {} entidades falló ({}), reintentando una a una",
+                    batch.len(),
+                    e
+                );
+
+                let mut outcomes = Vec::with_capacity(batch.len());
+                for entity in batch {
+                    let error = self
+                        .write(std::slice::from_ref(&entity))
+                        .await
+                        .err()
+                        .map(|e| e.to_string());
+                    outcomes.push(WriteOutcome {
+                        name: entity.name,
+                        error,
+                    });
+                }
+                outcomes
+            }
+        }
+    }
+
+    // Colecciones en paralelo hacen MERGE de los mismos nodos compartidos
+    // (Manufacturer, Character, Film): los deadlocks y demás errores transitorios
+    // se reintentan con la transacción entera.
+    async fn write(&self, batch: &[PendingEntity]) -> Result<()> {
+        let mut attempt = 1;
+        loop {
+            match self.write_once(batch).await {
+                Err(e) if attempt < WRITE_ATTEMPTS && is_transient(&e) => {
+                    eprintln!(
+                        "⚠️ Error transitorio de Neo4j (intento {} de {}): {}",
+                        attempt, WRITE_ATTEMPTS, e
+                    );
+                    tokio::time::sleep(WRITE_RETRY_DELAY * attempt).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn write_once(&self, batch: &[PendingEntity]) -> Result<()> {
+        let mut txn = self.graph.start_txn().await?;
+        let queries = build_queries(&self.collection, &self.run_id, self.reconcile_edges, batch);
+        txn.run_queries(queries).await?;
+        txn.commit().await?;
+        Ok(())
+    }
+}
+
+const WRITE_ATTEMPTS: u32 = 4;
+const WRITE_RETRY_DELAY: Duration = Duration::from_millis(200);
+
+fn is_transient(e: &anyhow::Error) -> bool {
+    matches!(
+        e.downcast_ref::<neo4rs::Error>(),
+        Some(neo4rs::Error::Neo4j(err)) if err.kind() == Neo4jErrorKind::Transient
+    )
+}
+
+fn build_queries(
+    collection: &str,
+    run_id: &str,
+    reconcile_edges: bool,
+    batch: &[PendingEntity],
+) -> Vec<Query> {
+    let mut nodes_by_label: BTreeMap<&Label, Vec<BoltType>> = BTreeMap::new();
+    let mut chunks_by_label: BTreeMap<&Label, Vec<BoltType>> = BTreeMap::new();
+    let mut edges_by_relation: BTreeMap<(&Label, RelationType, &Label), Vec<BoltType>> =
+        BTreeMap::new();
+
+    for entity in batch {
+        let mut row = BoltMap::new();
+        row.put("id".into(), entity.id.clone().into());
+        row.put("name".into(), entity.name.clone().into());
+        row.put("props".into(), entity.props.clone());
+        row.put("chunk_count".into(), (entity.chunks.len() as i64).into());
+        row.put("edge_keys".into(), edge_keys(&entity.edges).into());
+        put_embedding(&mut row, &entity.embedding);
+        nodes_by_label
+            .entry(&entity.label)
+            .or_default()
+            .push(BoltType::Map(row));
+
+        for chunk in &entity.chunks {
+            let mut row = BoltMap::new();
+            row.put(
+                "id".into(),
+                format!("{}#chunk_{}", entity.id, chunk.ordinal).into(),
+            );
+            row.put("entity_id".into(), entity.id.clone().into());
+            row.put("ordinal".into(), chunk.ordinal.into());
+            row.put("text".into(), chunk.text.clone().into());
+            put_embedding(&mut row, &chunk.embedding);
+            chunks_by_label
+                .entry(&entity.label)
+                .or_default()
+                .push(BoltType::Map(row));
+        }
+
+        for edge in &entity.edges {
+            let mut row = BoltMap::new();
+            row.put("source_id".into(), edge.source_id.clone().into());
+            row.put("target_id".into(), edge.target_id.clone().into());
+            row.put("source_name".into(), edge.source_name.clone().into());
+            row.put("target_name".into(), edge.target_name.clone().into());
+            row.put("asserted_by".into(), entity.id.clone().into());
+            row.put("props".into(), edge_properties(collection, entity, edge));
+            edges_by_relation
+                .entry((&edge.source_label, edge.relation_type, &edge.target_label))
+                .or_default()
+                .push(BoltType::Map(row));
+        }
+    }
+
+    let mut queries = Vec::new();
+
+    for (label, rows) in nodes_by_label {
+        let label = quote_identifier(label.as_str());
+        let node_query_str = format!(
+            "UNWIND $rows AS row
+             MERGE (n:{label} {{id: row.id}})
+             SET n:{ENTITY_LABEL},
+                 n += row.props,
+                 n.embedding = row.vector,
+                 n.embedding_status = row.embedding_status,
+                 n.embedding_error = row.embedding_error,
+                 n.name = row.name,
+                 n.placeholder = null,
+                 n.source_collection = $collection,
+                 n.ingest_run = $run_id,
+                 n.archived = null,
+                 n.archived_at = null,
+                 n.last_updated = timestamp()"
+        );
+        queries.push(
+            query(&node_query_str)
+                .param("rows", rows.clone())
+                .param("collection", collection)
+                .param("run_id", run_id),
+        );
+
+        // Chunks sobrantes de una versión anterior (más larga) del texto
+        let prune_query_str = format!(
+            "UNWIND $rows AS row
+             MATCH (n:{label} {{id: row.id}})-[:HAS_CHUNK]->(c:Chunk)
+             WHERE c.ordinal >= row.chunk_count
+             DETACH DELETE c"
+        );
+        queries.push(query(&prune_query_str).param("rows", rows.clone()));
+
+        // Relaciones que la entidad afirmó antes y ya no están en get_edges().
+        // Solo las propias (asserted_by): las de otras colecciones no se tocan.
+        if reconcile_edges {
+            let stale_edges_str = format!(
+                "UNWIND $rows AS row
+                 MATCH (n:{label} {{id: row.id}})-[r]-()
+                 WHERE r.asserted_by = row.id
+                   AND NOT type(r) + '|' + startNode(r).id + '|' + endNode(r).id IN row.edge_keys
+                 DELETE r"
+            );
+            queries.push(query(&stale_edges_str).param("rows", rows));
+        }
+    }
+
+    for (label, rows) in chunks_by_label {
+        let label = quote_identifier(label.as_str());
+        let chunk_query_str = format!(
+            "UNWIND $rows AS row
+             MATCH (n:{label} {{id: row.entity_id}})
+             MERGE (c:{CHUNK_LABEL} {{id: row.id}})
+             SET c.text = row.text,
+                 c.ordinal = row.ordinal,
+                 c.entity_id = row.entity_id,
+                 c.embedding = row.vector,
+                 c.embedding_status = row.embedding_status,
+                 c.embedding_error = row.embedding_error,
+                 c.last_updated = timestamp()
+             MERGE (n)-[:HAS_CHUNK]->(c)"
+        );
+        queries.push(query(&chunk_query_str).param("rows", rows));
+    }
+
+    // Un nodo creado solo por una relación queda como placeholder hasta que llegue
+    // su documento (salvo atributos y personas, que ya vienen con nombre).
+    // Una relación de antes de `asserted_by` se adopta en vez de duplicarla.
+    for ((source_label, relation, target_label), rows) in edges_by_relation {
+        let source_label = quote_identifier(source_label.as_str());
+        let relation = quote_identifier(relation.as_str());
+        let target_label = quote_identifier(target_label.as_str());
+        let edge_query_str = format!(
+            "UNWIND $rows AS row
+             MERGE (source:{source_label} {{id: row.source_id}})
+             ON CREATE SET source.placeholder = CASE WHEN row.source_name IS NULL THEN true END
+             SET source.name = coalesce(source.name, row.source_name)
+             MERGE (target:{target_label} {{id: row.target_id}})
+             ON CREATE SET target.placeholder = CASE WHEN row.target_name IS NULL THEN true END
+             SET target.name = coalesce(target.name, row.target_name)
+             WITH source, target, row
+             OPTIONAL MATCH (source)-[legacy:{relation}]->(target)
+             WHERE legacy.asserted_by IS NULL
+             WITH source, target, row, head(collect(legacy)) AS legacy
+             FOREACH (l IN CASE WHEN legacy IS NULL THEN [] ELSE [legacy] END |
+                 SET l.asserted_by = row.asserted_by)
+             MERGE (source)-[r:{relation} {{asserted_by: row.asserted_by}}]->(target)
+             ON CREATE SET r.first_seen = timestamp()
+             SET r += row.props,
+                 r.last_seen = timestamp()"
+        );
+        queries.push(query(&edge_query_str).param("rows", rows));
+    }
+
+    queries
+}
+
+// Identidad de cada relación afirmada, con el mismo formato que arma el Cypher
+fn edge_keys(edges: &[GraphEdge]) -> Vec<String> {
+    edges
+        .iter()
+        .map(|edge| {
+            format!(
+                "{}|{}|{}",
+                edge.relation_type.as_str(),
+                edge.source_id,
+                edge.target_id
+            )
+        })
+        .collect()
+}
+
+// Sin vector se borra la propiedad; el estado deja claro por qué
+fn put_embedding(row: &mut BoltMap, embedding: &NodeEmbedding) {
+    let (vector, error) = match embedding {
+        NodeEmbedding::Vector(v) => (Some(v.clone()), None),
+        NodeEmbedding::Failed(e) => (None, Some(e.clone())),
+        NodeEmbedding::NoText => (None, None),
+    };
+    row.put("vector".into(), vector.into());
+    row.put("embedding_status".into(), embedding.status().into());
+    row.put("embedding_error".into(), error.into());
+}
+
+// Las claves terminan como nombres de propiedad en Neo4j; se normalizan igual que
+// cualquier otro identificador y se descartan las que quedan vacías.
+fn escape_property_keys(map: serde_json::Map<String, Value>) -> serde_json::Map<String, Value> {
+    map.into_iter()
+        .filter_map(|(key, value)| escape_property_key(&key).map(|key| (key, value)))
+        .collect()
+}
+
+// Metadata JSON más las fechas, que se agregan ya como `date` de Neo4j
+fn node_properties(
+    metadata: serde_json::Map<String, Value>,
+    dates: Vec<(String, Option<NaiveDate>)>,
+) -> BoltType {
+    let mut props = json_map_to_bolt_type(metadata);
+    if let BoltType::Map(map) = &mut props {
+        for (key, date) in dates {
+            let Some(key) = escape_property_key(&key) else {
+                continue;
+            };
+            let value = match date {
+                Some(date) => BoltType::Date(BoltDate::from(date)),
+                None => BoltType::Null(BoltNull),
+            };
+            map.put(key.into(), value);
+        }
+    }
+    props
+}
+
+// Propiedades propias del edge más su procedencia. Cada entidad escribe su propia
+// relación (clave `asserted_by`), así un mismo vínculo afirmado desde dos colecciones
+// queda como dos relaciones auditables.
+fn edge_properties(collection: &str, entity: &PendingEntity, edge: &GraphEdge) -> BoltType {
+    let mut props = escape_property_keys(edge.properties.clone());
+    props.insert("source_collection".to_string(), Value::from(collection));
+    props.insert(
+        "source_oid".to_string(),
+        entity
+            .source_oid
+            .clone()
+            .map(Value::from)
+            .unwrap_or(Value::Null),
+    );
+    props.insert("asserted_by".to_string(), Value::from(entity.id.as_str()));
+    json_map_to_bolt_type(props)
+}
@@ -0,0 +1,271 @@
+use crate::{
+    jobs::{ChildJob, IngestJob, JobControl, JobStatus, StopRequest},
+    reconcile::{ReconcileOptions, remove_legacy_edges},
+    services::{lease_lost_error, run_ingest_job, spawn_lease_heartbeat},
+    state::AppState,
+};
+use anyhow::Result;
+use futures::future::join_all;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+
+// Colección "virtual" del job padre de POST /ingest (también es su lease)
+pub const ALL_COLLECTIONS: &str = "*";
+
+// Cada etapa crea los nodos que referencian las siguientes (p.ej. BORN_ON de
+// characters necesita los planetas). Las colecciones de una misma etapa corren en
+// paralelo y pueden hacer MERGE de los mismos nodos (Manufacturer, Character, Film):
+// los deadlocks que eso provoque los reintenta el GraphBatchWriter.
+const INGEST_STAGES: [&[&str]; 4] = [
+    &["movies_raw"],
+    &["planets_raw"],
+    &["species_raw", "starships_raw", "vehicles_raw"],
+    &["characters_raw"],
+];
+
+// Las colecciones del mapping pueden apuntar a cualquiera de las tipadas: van al final
+pub fn ingest_stages(state: &AppState) -> Vec<Vec<String>> {
+    let mut stages: Vec<Vec<String>> = INGEST_STAGES
+        .iter()
+        .map(|stage| stage.iter().map(|c| c.to_string()).collect())
+        .collect();
+
+    let mapped: Vec<String> = state
+        .mappings
+        .collections
+        .keys()
+        .filter(|name| !stages.iter().flatten().any(|typed| typed == *name))
+        .cloned()
+        .collect();
+    if !mapped.is_empty() {
+        stages.push(mapped);
+    }
+    stages
+}
+
+// Arma el padre y sus hijos (pendientes) para que la API pueda devolver todos los ids
+pub fn plan_ingest_all(
+    state: &AppState,
+    reconcile: Option<ReconcileOptions>,
+) -> (IngestJob, Vec<IngestJob>) {
+    let mut parent = IngestJob::new(ALL_COLLECTIONS);
+    parent.reconcile = reconcile;
+
+    let mut children = Vec::new();
+    for (stage, collections) in ingest_stages(state).into_iter().enumerate() {
+        for collection in collections {
+            let mut child = IngestJob::new(&collection);
+            child.reconcile = reconcile;
+            child.parent_id = Some(parent.id.clone());
+            parent.children.push(ChildJob::from_job(&child, stage));
+            children.push(child);
+        }
+    }
+
+    (parent, children)
+}
+
+pub async fn run_ingest_all(state: Arc<AppState>, mut parent: IngestJob) {
+    parent.start();
+    if let Err(e) = state.jobs.save(&parent).await {
+        eprintln!("⚠️ No se pudo guardar el job {}: {}", parent.id, e);
+    }
+
+    let control = state.job_registry.register(&parent);
+    let heartbeat = spawn_lease_heartbeat(state.clone(), &parent);
+
+    println!(">>> Ingesta completa (job {})", parent.id);
+    let result = run_stages(&state, &mut parent, &control).await;
+    if matches!(result, Ok(None)) && state.ingest.reconcile_edges {
+        match remove_legacy_edges(&state.graph).await {
+            Ok(removed) => println!("🧹 {} relaciones sin asserted_by eliminadas", removed),
+            Err(e) => eprintln!("⚠️ No se pudieron limpiar las relaciones antiguas: {}", e),
+        }
+    }
+    match &result {
+        Err(e) => eprintln!("Ingesta completa fallida: {:#}", e),
+        Ok(Some(stop)) => println!("⏹️ Ingesta completa detenida ({:?})", stop),
+        Ok(None) => println!(
+            "<<< Ingesta completa: {} procesados, {} fallidos, {} inválidos",
+            parent.processed, parent.failed, parent.skipped
+        ),
+    }
+
+    parent.finish(&result);
+    if heartbeat.is_finished() {
+        parent.last_error = Some(lease_lost_error(ALL_COLLECTIONS));
+    }
+    if let Err(e) = state.jobs.save(&parent).await {
+        eprintln!("⚠️ No se pudo guardar el job {}: {}", parent.id, e);
+    }
+    heartbeat.abort();
+    state.job_registry.unregister(&parent.id);
+    if let Err(e) = state
+        .job_registry
+        .release(ALL_COLLECTIONS, &parent.id)
+        .await
+    {
+        eprintln!(
+            "⚠️ No se pudo liberar el lease de la ingesta completa: {}",
+            e
+        );
+    }
+}
+
+// Una etapa arranca cuando la anterior terminó completa. Los hijos ya completados
+// (al retomar el padre) se saltan; los pausados o fallidos se retoman.
+async fn run_stages(
+    state: &Arc<AppState>,
+    parent: &mut IngestJob,
+    control: &JobControl,
+) -> Result<Option<StopRequest>> {
+    let stages = parent
+        .children
+        .iter()
+        .map(|c| c.stage)
+        .max()
+        .map_or(0, |s| s + 1);
+
+    for stage in 0..stages {
+        if let Some(stop) = control.stop_requested() {
+            return Ok(Some(stop));
+        }
+
+        let ids: Vec<String> = parent
+            .children
+            .iter()
+            .filter(|c| c.stage == stage)
+            .map(|c| c.job_id.clone())
+            .collect();
+
+        let mut started = Vec::new();
+        let mut handles = Vec::new();
+        let mut launch_error = None;
+        for id in &ids {
+            match start_child(state, id).await {
+                Ok(Some((child_id, handle))) => {
+                    started.push(child_id);
+                    handles.push(handle);
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    launch_error = Some(e);
+                    break;
+                }
+            }
+        }
+
+        // Si no se pudo lanzar la etapa entera, los hijos ya lanzados se pausan
+        // (guardan checkpoint) antes de soltar el lease del padre
+        if launch_error.is_some() {
+            for id in &started {
+                state.job_registry.request_stop(id, StopRequest::Pause);
+            }
+        }
+
+        let stage_done = join_all(handles);
+        tokio::pin!(stage_done);
+        tokio::select! {
+            _ = &mut stage_done => {}
+            _ = control.cancelled() => {
+                // Se reenvía a los hijos; cada uno guarda su estado al detenerse
+                if let Some(stop) = control.stop_requested() {
+                    for id in &started {
+                        state.job_registry.request_stop(id, stop);
+                    }
+                }
+                stage_done.await;
+            }
+        }
+
+        refresh_children(state, parent).await?;
+        if let Err(e) = state.jobs.save(parent).await {
+            eprintln!(
+                "⚠️ No se pudo guardar el progreso del job {}: {}",
+                parent.id, e
+            );
+        }
+
+        if let Some(e) = launch_error {
+            return Err(e);
+        }
+        if let Some(stop) = control.stop_requested() {
+            return Ok(Some(stop));
+        }
+
+        let incomplete: Vec<&str> = parent
+            .children
+            .iter()
+            .filter(|c| c.stage == stage && c.status != JobStatus::Completed)
+            .map(|c| c.collection.as_str())
+            .collect();
+        if !incomplete.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Etapa {} incompleta ({}): no se ingestan las colecciones que dependen de ella",
+                stage + 1,
+                incomplete.join(", ")
+            ));
+        }
+    }
+
+    Ok(None)
+}
+
+// Reclama el lease del hijo y lo lanza; None si ya estaba completo o la colección
+// está ocupada (queda fallido)
+async fn start_child(state: &Arc<AppState>, id: &str) -> Result<Option<(String, JoinHandle<()>)>> {
+    let Some(mut child) = state.jobs.get(id).await? else {
+        return Err(anyhow::anyhow!("Job hijo {} no encontrado", id));
+    };
+    if child.status == JobStatus::Completed {
+        return Ok(None);
+    }
+    if child.status != JobStatus::Pending {
+        child.resume();
+    }
+
+    if let Some(existing) = state
+        .job_registry
+        .claim(&child.collection, &child.id)
+        .await?
+    {
+        child.finish(&Err(anyhow::anyhow!(
+            "Ya hay una ingesta en curso para {} (job {})",
+            child.collection,
+            existing
+        )));
+        state.jobs.save(&child).await?;
+        return Ok(None);
+    }
+
+    if let Err(e) = state.jobs.save(&child).await {
+        // Sin el job guardado no se lanza: se suelta el lease recién tomado
+        if let Err(e) = state
+            .job_registry
+            .release(&child.collection, &child.id)
+            .await
+        {
+            eprintln!(
+                "⚠️ No se pudo liberar el lease de {}: {}",
+                child.collection, e
+            );
+        }
+        return Err(e);
+    }
+    let child_id = child.id.clone();
+    Ok(Some((
+        child_id,
+        tokio::spawn(run_ingest_job(state.clone(), child)),
+    )))
+}
+
+async fn refresh_children(state: &AppState, parent: &mut IngestJob) -> Result<()> {
+    let mut children = Vec::with_capacity(parent.children.len());
+    for summary in &parent.children {
+        if let Some(child) = state.jobs.get(&summary.job_id).await? {
+            children.push(child);
+        }
+    }
+    parent.absorb_children(&children);
+    Ok(())
+}
@@ -0,0 +1,556 @@
+use crate::{
+    reconcile::{ReconcileOptions, ReconcileReport},
+    utils::now_millis,
+};
+use anyhow::Result;
+use futures::stream::TryStreamExt;
+use mongodb::{
+    Collection, Database,
+    bson::{Bson, Document, doc, oid::ObjectId},
+    error::{ErrorKind, WriteFailure},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, OnceLock},
+    time::Duration,
+};
+use tokio_util::sync::CancellationToken;
+
+pub const JOBS_COLLECTION: &str = "ingest_jobs";
+pub const LEASES_COLLECTION: &str = "ingest_leases";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+    // Detenido a pedido; un job pausado se puede retomar desde su checkpoint
+    Cancelled,
+    Paused,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StopRequest {
+    Cancel,
+    Pause,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IngestJob {
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub collection: String,
+    pub status: JobStatus,
+    pub created_at: i64,
+    pub started_at: Option<i64>,
+    pub finished_at: Option<i64>,
+    pub processed: u64,
+    pub failed: u64,
+    pub skipped: u64,
+    pub last_error: Option<String>,
+    #[serde(default)]
+    pub embedded: u64,
+    #[serde(default)]
+    pub embedding_failed: u64,
+    #[serde(default)]
+    pub chunks: u64,
+    #[serde(default)]
+    pub embedding_calls: u64,
+    #[serde(default)]
+    pub cache_hits: u64,
+    #[serde(default)]
+    pub docs_per_second: Option<f64>,
+    // Borrado/archivado de nodos que la corrida no vio
+    #[serde(default)]
+    pub reconcile: Option<ReconcileOptions>,
+    #[serde(default)]
+    pub reconciled: Option<ReconcileReport>,
+    // Último `_id` escrito en el grafo; desde ahí sigue un resume
+    #[serde(default)]
+    pub checkpoint: Option<JobCheckpoint>,
+    #[serde(default)]
+    pub resumes: u32,
+    // Jobs de POST /ingest: el padre lista sus hijos, cada hijo apunta al padre
+    #[serde(default)]
+    pub parent_id: Option<String>,
+    #[serde(default)]
+    pub children: Vec<ChildJob>,
+}
+
+// Resumen de un job hijo dentro del padre
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChildJob {
+    pub job_id: String,
+    pub collection: String,
+    // Etapa en el orden de dependencias (0 primero)
+    pub stage: usize,
+    pub status: JobStatus,
+    pub processed: u64,
+    pub failed: u64,
+    pub skipped: u64,
+    pub last_error: Option<String>,
+}
+
+impl ChildJob {
+    pub fn from_job(job: &IngestJob, stage: usize) -> Self {
+        Self {
+            job_id: job.id.clone(),
+            collection: job.collection.clone(),
+            stage,
+            status: job.status,
+            processed: job.processed,
+            failed: job.failed,
+            skipped: job.skipped,
+            last_error: job.last_error.clone(),
+        }
+    }
+}
+
+// Contadores al momento del checkpoint: al retomar se vuelve a ellos para no
+// contar dos veces los documentos posteriores
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobCheckpoint {
+    pub last_id: Bson,
+    pub processed: u64,
+    pub failed: u64,
+    pub skipped: u64,
+    #[serde(default)]
+    pub embedded: u64,
+    #[serde(default)]
+    pub embedding_failed: u64,
+    #[serde(default)]
+    pub chunks: u64,
+    #[serde(default)]
+    pub embedding_calls: u64,
+    #[serde(default)]
+    pub cache_hits: u64,
+    pub saved_at: i64,
+}
+
+// Avance de una pasada ordenada por `_id`. Los documentos inválidos se cuentan
+// con el grupo que los sigue y recién cuando ese grupo se escribe: así el
+// checkpoint (último `_id` escrito) y sus contadores cubren el mismo prefijo.
+#[derive(Debug, Default)]
+pub struct PassProgress {
+    pending_skipped: u64,
+    pub last_written_id: Option<Bson>,
+}
+
+// Cierre de un grupo: `_id` de su último documento y los inválidos que lo preceden
+#[derive(Debug)]
+pub struct GroupMark {
+    last_id: Option<Bson>,
+    skipped: u64,
+}
+
+impl PassProgress {
+    pub fn skip(&mut self) {
+        self.pending_skipped += 1;
+    }
+
+    pub fn close_group(&mut self, last_id: Option<Bson>) -> GroupMark {
+        GroupMark {
+            last_id,
+            skipped: std::mem::take(&mut self.pending_skipped),
+        }
+    }
+
+    pub fn written(&mut self, job: &mut IngestJob, mark: GroupMark) {
+        job.skipped += mark.skipped;
+        if mark.last_id.is_some() {
+            self.last_written_id = mark.last_id;
+        }
+    }
+
+    // Pasada completa: los inválidos del final no tienen grupo detrás
+    pub fn finish(&mut self, job: &mut IngestJob) {
+        job.skipped += std::mem::take(&mut self.pending_skipped);
+    }
+}
+
+impl IngestJob {
+    pub fn new(collection: &str) -> Self {
+        Self {
+            id: ObjectId::new().to_hex(),
+            collection: collection.to_string(),
+            status: JobStatus::Pending,
+            created_at: now_millis(),
+            started_at: None,
+            finished_at: None,
+            processed: 0,
+            failed: 0,
+            skipped: 0,
+            last_error: None,
+            embedded: 0,
+            embedding_failed: 0,
+            chunks: 0,
+            embedding_calls: 0,
+            cache_hits: 0,
+            docs_per_second: None,
+            reconcile: None,
+            reconciled: None,
+            checkpoint: None,
+            resumes: 0,
+            parent_id: None,
+            children: Vec::new(),
+        }
+    }
+
+    pub fn start(&mut self) {
+        self.status = JobStatus::Running;
+        self.started_at = Some(now_millis());
+    }
+
+    // `Some` si el job se detuvo a pedido antes de terminar la colección
+    pub fn finish(&mut self, result: &Result<Option<StopRequest>>) {
+        let finished_at = now_millis();
+        self.finished_at = Some(finished_at);
+
+        if let Some(started_at) = self.started_at {
+            let elapsed_secs = (finished_at - started_at).max(1) as f64 / 1000.0;
+            self.docs_per_second = Some(self.seen() as f64 / elapsed_secs);
+        }
+
+        match result {
+            Ok(None) => self.status = JobStatus::Completed,
+            Ok(Some(StopRequest::Cancel)) => self.status = JobStatus::Cancelled,
+            Ok(Some(StopRequest::Pause)) => self.status = JobStatus::Paused,
+            Err(e) => {
+                self.status = JobStatus::Failed;
+                self.last_error = Some(e.to_string());
+            }
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(self.status, JobStatus::Completed | JobStatus::Cancelled)
+    }
+
+    // Vuelve a pendiente conservando el checkpoint (o desde cero si no hay)
+    pub fn resume(&mut self) {
+        let checkpoint = self.checkpoint.as_ref();
+        let counter = |get: fn(&JobCheckpoint) -> u64| checkpoint.map_or(0, get);
+        self.processed = counter(|c| c.processed);
+        self.failed = counter(|c| c.failed);
+        self.skipped = counter(|c| c.skipped);
+        self.embedded = counter(|c| c.embedded);
+        self.embedding_failed = counter(|c| c.embedding_failed);
+        self.chunks = counter(|c| c.chunks);
+        self.embedding_calls = counter(|c| c.embedding_calls);
+        self.cache_hits = counter(|c| c.cache_hits);
+        self.status = JobStatus::Pending;
+        self.finished_at = None;
+        self.last_error = None;
+        self.reconciled = None;
+        self.resumes += 1;
+    }
+
+    pub fn set_checkpoint(&mut self, last_id: Bson) {
+        self.checkpoint = Some(JobCheckpoint {
+            last_id,
+            processed: self.processed,
+            failed: self.failed,
+            skipped: self.skipped,
+            embedded: self.embedded,
+            embedding_failed: self.embedding_failed,
+            chunks: self.chunks,
+            embedding_calls: self.embedding_calls,
+            cache_hits: self.cache_hits,
+            saved_at: now_millis(),
+        });
+    }
+
+    // El padre suma los contadores de sus hijos y actualiza sus resúmenes
+    pub fn absorb_children(&mut self, children: &[IngestJob]) {
+        for child in children {
+            if let Some(summary) = self.children.iter_mut().find(|c| c.job_id == child.id) {
+                *summary = ChildJob::from_job(child, summary.stage);
+            }
+        }
+
+        self.processed = children.iter().map(|c| c.processed).sum();
+        self.failed = children.iter().map(|c| c.failed).sum();
+        self.skipped = children.iter().map(|c| c.skipped).sum();
+        self.embedded = children.iter().map(|c| c.embedded).sum();
+        self.embedding_failed = children.iter().map(|c| c.embedding_failed).sum();
+        self.chunks = children.iter().map(|c| c.chunks).sum();
+        self.embedding_calls = children.iter().map(|c| c.embedding_calls).sum();
+        self.cache_hits = children.iter().map(|c| c.cache_hits).sum();
+    }
+
+    pub fn seen(&self) -> u64 {
+        self.processed + self.failed + self.skipped
+    }
+
+    pub fn record_failure(&mut self, error: impl ToString) {
+        self.failed += 1;
+        self.last_error = Some(error.to_string());
+    }
+}
+
+// Persistencia de los jobs en Mongo, junto a las colecciones raw
+#[derive(Clone)]
+pub struct JobStore {
+    collection: Collection<IngestJob>,
+}
+
+impl JobStore {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection::<IngestJob>(JOBS_COLLECTION),
+        }
+    }
+
+    pub async fn save(&self, job: &IngestJob) -> Result<()> {
+        self.collection
+            .replace_one(doc! { "_id": &job.id }, job)
+            .upsert(true)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get(&self, id: &str) -> Result<Option<IngestJob>> {
+        Ok(self.collection.find_one(doc! { "_id": id }).await?)
+    }
+
+    pub async fn list(&self, collection: Option<&str>, limit: i64) -> Result<Vec<IngestJob>> {
+        let mut filter = Document::new();
+        if let Some(name) = collection {
+            filter.insert("collection", name);
+        }
+
+        let cursor = self
+            .collection
+            .find(filter)
+            .sort(doc! { "created_at": -1 })
+            .limit(limit)
+            .await?;
+
+        Ok(cursor.try_collect().await?)
+    }
+}
+
+// Control de un job en ejecución: el loop de ingesta consulta el token entre documentos
+#[derive(Clone, Default)]
+pub struct JobControl {
+    token: CancellationToken,
+    request: Arc<OnceLock<StopRequest>>,
+}
+
+impl JobControl {
+    // Gana el primer pedido: pausar un job que ya se está cancelando no lo cambia
+    fn request(&self, stop: StopRequest) -> StopRequest {
+        let stop = *self.request.get_or_init(|| stop);
+        self.token.cancel();
+        stop
+    }
+
+    pub async fn cancelled(&self) {
+        self.token.cancelled().await
+    }
+
+    pub fn stop_requested(&self) -> Option<StopRequest> {
+        if self.token.is_cancelled() {
+            self.request.get().copied()
+        } else {
+            None
+        }
+    }
+}
+
+// Lease por colección en Mongo: una sola ingesta a la vez aunque haya varias réplicas.
+// Vence si el dueño deja de renovarlo (proceso caído).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CollectionLease {
+    #[serde(rename = "_id")]
+    collection: String,
+    job_id: String,
+    owner: String,
+    expires_at: i64,
+}
+
+// Jobs que corren en este proceso y leases de sus colecciones
+#[derive(Clone)]
+pub struct JobRegistry {
+    running: Arc<Mutex<HashMap<String, JobControl>>>,
+    leases: Collection<CollectionLease>,
+    // Identifica a esta réplica en los leases
+    owner: String,
+    lease_ttl_ms: i64,
+}
+
+impl JobRegistry {
+    pub fn new(db: &Database, lease_ttl_secs: u64) -> Self {
+        Self {
+            running: Arc::new(Mutex::new(HashMap::new())),
+            leases: db.collection::<CollectionLease>(LEASES_COLLECTION),
+            owner: ObjectId::new().to_hex(),
+            lease_ttl_ms: lease_ttl_secs.max(1) as i64 * 1000,
+        }
+    }
+
+    // Toma el lease de la colección para el job. Si otro job lo tiene vigente
+    // devuelve su id. Solo se retoma un lease vencido o de esta misma réplica:
+    // un resume mandado a otra réplica (o dos veces) no le roba el lease a
+    // quien todavía lo renueva.
+    pub async fn claim(&self, collection: &str, job_id: &str) -> Result<Option<String>> {
+        // Reserva local por job_id: cubre dos pedidos simultáneos del mismo job en
+        // esta réplica. Entre jobs distintos de la colección decide el lease.
+        {
+            let mut running = self.lock();
+            if running.contains_key(job_id) {
+                return Ok(Some(job_id.to_string()));
+            }
+            running.insert(job_id.to_string(), JobControl::default());
+        }
+
+        let now = now_millis();
+        let result = self
+            .leases
+            .update_one(
+                doc! {
+                    "_id": collection,
+                    "$or": [
+                        { "expires_at": { "$lt": now } },
+                        { "job_id": job_id, "owner": &self.owner },
+                    ],
+                },
+                doc! {
+                    "$set": {
+                        "job_id": job_id,
+                        "owner": &self.owner,
+                        "expires_at": now + self.lease_ttl_ms,
+                    }
+                },
+            )
+            .upsert(true)
+            .await;
+
+        match result {
+            Ok(_) => Ok(None),
+            // El upsert choca con el `_id` de un lease ajeno todavía vigente
+            Err(e) if is_duplicate_key(&e) => {
+                self.unregister(job_id);
+                let holder = self.leases.find_one(doc! { "_id": collection }).await?;
+                Ok(Some(holder.map(|lease| lease.job_id).unwrap_or_default()))
+            }
+            Err(e) => {
+                self.unregister(job_id);
+                Err(e.into())
+            }
+        }
+    }
+
+    // false si el lease ya no es de este job (venció y lo tomó otro)
+    pub async fn renew(&self, collection: &str, job_id: &str) -> Result<bool> {
+        let result = self
+            .leases
+            .update_one(
+                doc! { "_id": collection, "job_id": job_id },
+                doc! { "$set": { "expires_at": now_millis() + self.lease_ttl_ms } },
+            )
+            .await?;
+        Ok(result.matched_count > 0)
+    }
+
+    pub async fn release(&self, collection: &str, job_id: &str) -> Result<()> {
+        self.unregister(job_id);
+        self.leases
+            .delete_one(doc! { "_id": collection, "job_id": job_id })
+            .await?;
+        Ok(())
+    }
+
+    // Cada cuánto renovar: un tercio del TTL deja margen para un par de fallos
+    pub fn renew_every(&self) -> Duration {
+        Duration::from_millis((self.lease_ttl_ms / 3).max(1) as u64)
+    }
+
+    // Reutiliza el control reservado en `claim`: un cancel que llegó antes de
+    // que arranque el job no se pierde
+    pub fn register(&self, job: &IngestJob) -> JobControl {
+        self.lock().entry(job.id.clone()).or_default().clone()
+    }
+
+    pub fn unregister(&self, id: &str) {
+        self.lock().remove(id);
+    }
+
+    pub fn is_running(&self, id: &str) -> bool {
+        self.lock().contains_key(id)
+    }
+
+    // None si el job no está corriendo en este proceso
+    pub fn request_stop(&self, id: &str, stop: StopRequest) -> Option<StopRequest> {
+        self.lock().get(id).map(|control| control.request(stop))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, JobControl>> {
+        self.running.lock().expect("registro de jobs envenenado")
+    }
+}
+
+fn is_duplicate_key(error: &mongodb::error::Error) -> bool {
+    matches!(
+        error.kind.as_ref(),
+        ErrorKind::Write(WriteFailure::WriteError(e)) if e.code == 11000
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Grupos de un documento; `(id, false)` es un documento inválido
+    fn run_pass(job: &mut IngestJob, docs: &[(i32, bool)], pause_after: Option<i32>) {
+        let mut pass = PassProgress::default();
+        for &(id, valid) in docs {
+            if !valid {
+                pass.skip();
+                continue;
+            }
+            let mark = pass.close_group(Some(Bson::Int32(id)));
+            job.processed += 1;
+            pass.written(job, mark);
+            if pause_after == Some(id) {
+                job.set_checkpoint(pass.last_written_id.clone().unwrap());
+                job.finish(&Ok(Some(StopRequest::Pause)));
+                return;
+            }
+        }
+        pass.finish(job);
+        job.finish(&Ok(None));
+    }
+
+    #[test]
+    fn skipped_documents_are_not_counted_twice_on_resume() {
+        // 2 y 4 son inválidos; la pausa llega con el grupo de 3 escrito
+        let docs = [(1, true), (2, false), (3, true), (4, false), (5, true)];
+
+        let mut job = IngestJob::new("characters_raw");
+        run_pass(&mut job, &docs[..4], Some(3));
+        assert_eq!(job.status, JobStatus::Paused);
+        let checkpoint = job.checkpoint.clone().unwrap();
+        assert_eq!(checkpoint.last_id, Bson::Int32(3));
+        assert_eq!((checkpoint.processed, checkpoint.skipped), (2, 1));
+
+        // Al retomar se relee desde el _id siguiente al checkpoint
+        job.resume();
+        let rest: Vec<(i32, bool)> = docs.into_iter().filter(|(id, _)| *id > 3).collect();
+        run_pass(&mut job, &rest, None);
+
+        assert_eq!(job.status, JobStatus::Completed);
+        assert_eq!((job.processed, job.skipped), (3, 2));
+    }
+
+    #[test]
+    fn trailing_skips_count_when_the_pass_finishes() {
+        let mut job = IngestJob::new("characters_raw");
+        run_pass(&mut job, &[(1, true), (2, false)], None);
+        assert_eq!((job.processed, job.skipped), (1, 1));
+    }
+}
@@ -1,8 +1,24 @@
 mod api;
+mod chunking;
+mod config;
+mod cypher;
+mod embedding_cache;
+mod embedding_retry;
+mod embeddings;
+mod graph_audit;
+mod graph_writer;
+mod ingest_all;
+mod jobs;
+mod mapping;
 mod models;
+mod normalize;
+mod reconcile;
+mod retrieval;
+mod schema;
 mod services;
 mod state;
 mod utils;
+mod watcher;
 
 use dotenvy::dotenv;
 use mongodb::{Client as MongoClient, options::ClientOptions};
@@ -19,18 +35,54 @@ async fn main() -> anyhow::Result<()> {
     let neo4j_uri = env::var("NEO4J_URI").expect("NEO4J_URI required");
     let neo4j_user = env::var("NEO4J_USER").expect("NEO4J_USER required");
     let neo4j_pass = env::var("NEO4J_PASSWORD").expect("NEO4J_PASSWORD required");
-    let gemini_key = env::var("GOOGLE_API_KEY").expect("GOOGLE_API_KEY required");
     let db_name = env::var("MONGO_DB_NAME").unwrap_or("starwars_db".to_string());
 
     let mongo_client = MongoClient::with_options(ClientOptions::parse(mongo_uri).await?)?;
     let graph = Arc::new(Graph::new(&neo4j_uri, &neo4j_user, &neo4j_pass).await?);
+    let embedder = embeddings::provider_from_config(&config::EmbeddingConfig::from_env()?)?;
 
-    let state = Arc::new(state::AppState {
-        mongo: mongo_client,
+    let ingest_config = config::IngestConfig::from_env();
+    normalize::configure_split_labels(ingest_config.split_attributes.clone());
+    // Antes del schema: los labels del mapping también llevan constraint
+    let mappings = mapping::MappingConfig::load(&ingest_config.mappings_path)?;
+    if !mappings.collections.is_empty() {
+        println!(
+            "🗺️ Colecciones mapeadas desde {}: {:?}",
+            ingest_config.mappings_path,
+            mappings.collections.keys().collect::<Vec<_>>()
+        );
+    }
+
+    let report = schema::bootstrap_schema(
+        &graph,
+        &mappings.labels,
+        embedder.dimension(),
+        &ingest_config.vector_similarity,
+    )
+    .await;
+    println!(
+        "🧱 Esquema Neo4j: {} constraints, {} índices vectoriales, {} fallidos",
+        report.constraints.len(),
+        report.vector_indexes.len(),
+        report.failures.len()
+    );
+    for drift in &report.drift {
+        eprintln!("⚠️ Drift de esquema: {}", drift);
+    }
+
+    let state = Arc::new(state::AppState::new(
+        mongo_client,
         graph,
-        gemini_key,
-        mongo_db_name: db_name,
-    });
+        embedder,
+        db_name,
+        ingest_config,
+        mappings,
+    ));
+
+    // Watchers que quedaron activos antes del reinicio
+    if let Err(e) = watcher::resume_watchers(state.clone()).await {
+        eprintln!("⚠️ No se pudieron retomar los watchers: {}", e);
+    }
 
     let app = api::app_router(state).layer(CorsLayer::permissive());
 
@@ -0,0 +1,411 @@
+use crate::models::{
+    GraphEdge, GraphableSource, Label, LabelRegistry, RelationType, TYPED_COLLECTIONS,
+};
+use anyhow::{Context, Result};
+use mongodb::bson::{Bson, Document};
+use serde::Deserialize;
+use serde_json::Value;
+use std::{collections::BTreeMap, path::Path, sync::Arc};
+
+// Formato del archivo de mapping (TOML). Ejemplo:
+//
+// [collections.droids_raw]
+// label = "Droid"
+// rich_text_fields = ["wiki_description"]
+// properties = ["model", "manufacturer"]
+//
+// [[collections.droids_raw.edges]]
+// field = "film_ids"
+// relation = "APPEARED_IN"
+// target = "Film"
+// prefix = "film_"
+#[derive(Debug, Default, Deserialize)]
+struct MappingFile {
+    #[serde(default)]
+    collections: BTreeMap<String, CollectionSpec>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CollectionSpec {
+    label: String,
+    #[serde(default = "default_id_field")]
+    id_field: String,
+    #[serde(default = "default_name_field")]
+    name_field: String,
+    #[serde(default)]
+    rich_text_fields: Vec<String>,
+    #[serde(default)]
+    properties: Vec<String>,
+    #[serde(default)]
+    edges: Vec<EdgeSpec>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EdgeSpec {
+    field: String,
+    relation: RelationType,
+    target: String,
+    #[serde(default)]
+    prefix: String,
+    #[serde(default)]
+    direction: EdgeDirection,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeDirection {
+    // (documento)-[rel]->(target)
+    #[default]
+    Outgoing,
+    // (target)-[rel]->(documento), p.ej. character_ids de una película
+    Incoming,
+}
+
+#[derive(Debug)]
+pub struct EdgeRule {
+    pub field: String,
+    pub relation: RelationType,
+    pub target: Label,
+    pub prefix: String,
+    pub direction: EdgeDirection,
+}
+
+#[derive(Debug)]
+pub struct CollectionMapping {
+    pub label: Label,
+    pub id_field: String,
+    pub name_field: String,
+    pub rich_text_fields: Vec<String>,
+    pub properties: Vec<String>,
+    pub edges: Vec<EdgeRule>,
+}
+
+#[derive(Debug, Default)]
+pub struct MappingConfig {
+    pub collections: BTreeMap<String, Arc<CollectionMapping>>,
+    // Allowlist de labels con los que declara este mapping
+    pub labels: LabelRegistry,
+}
+
+fn default_id_field() -> String {
+    "id".to_string()
+}
+
+fn default_name_field() -> String {
+    "name".to_string()
+}
+
+impl MappingConfig {
+    // Sin archivo no hay colecciones declarativas, solo los tipos en Rust
+    pub fn load(path: &str) -> Result<Self> {
+        if !Path::new(path).exists() {
+            return Ok(Self::default());
+        }
+
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("No se pudo leer el mapping {}", path))?;
+        Self::from_toml(&content).with_context(|| format!("Mapping inválido en {}", path))
+    }
+
+    pub fn from_toml(content: &str) -> Result<Self> {
+        let file: MappingFile = toml::from_str(content)?;
+
+        // decoder_for usa el tipo en Rust para estas colecciones: la entrada nunca se aplicaría
+        if let Some(name) = file
+            .collections
+            .keys()
+            .find(|name| TYPED_COLLECTIONS.contains(&name.as_str()))
+        {
+            return Err(anyhow::anyhow!(
+                "La colección {} ya tiene tipo en Rust y no se puede declarar en el mapping",
+                name
+            ));
+        }
+
+        // Primero se registran todos los labels para que un edge pueda apuntar
+        // a otra colección declarada en el mismo archivo
+        let mut registry = LabelRegistry::default();
+        let mut labels = BTreeMap::new();
+        for (name, spec) in &file.collections {
+            let label = registry
+                .register(&spec.label)
+                .with_context(|| format!("Colección {}", name))?;
+            labels.insert(name.clone(), label);
+        }
+
+        let mut collections = BTreeMap::new();
+        for (name, spec) in file.collections {
+            let mut edges = Vec::with_capacity(spec.edges.len());
+            for edge in spec.edges {
+                let target = registry
+                    .parse(&edge.target)
+                    .with_context(|| format!("Colección {}, campo {}", name, edge.field))?;
+                edges.push(EdgeRule {
+                    field: edge.field,
+                    relation: edge.relation,
+                    target,
+                    prefix: edge.prefix,
+                    direction: edge.direction,
+                });
+            }
+
+            let mapping = CollectionMapping {
+                label: labels.remove(&name).expect("label registrado arriba"),
+                id_field: spec.id_field,
+                name_field: spec.name_field,
+                rich_text_fields: spec.rich_text_fields,
+                properties: spec.properties,
+                edges,
+            };
+            collections.insert(name, Arc::new(mapping));
+        }
+
+        Ok(Self {
+            collections,
+            labels: registry,
+        })
+    }
+
+    pub fn get(&self, collection: &str) -> Option<Arc<CollectionMapping>> {
+        self.collections.get(collection).cloned()
+    }
+}
+
+// Documento de Mongo interpretado según su mapping
+pub struct MappedDocument {
+    mapping: Arc<CollectionMapping>,
+    id: String,
+    name: String,
+    doc: Document,
+}
+
+impl MappedDocument {
+    pub fn new(mapping: Arc<CollectionMapping>, doc: Document) -> Result<Self> {
+        let id = doc
+            .get(&mapping.id_field)
+            .and_then(bson_to_id)
+            .ok_or_else(|| anyhow::anyhow!("Documento sin campo id '{}'", mapping.id_field))?;
+        let name = doc
+            .get_str(&mapping.name_field)
+            .map(str::to_string)
+            .unwrap_or_else(|_| id.clone());
+
+        Ok(Self {
+            mapping,
+            id,
+            name,
+            doc,
+        })
+    }
+
+    // Un campo de edge puede ser un id suelto o una lista de ids
+    fn edge_values(&self, field: &str) -> Vec<String> {
+        match self.doc.get(field) {
+            Some(Bson::Array(items)) => items.iter().filter_map(bson_to_id).collect(),
+            Some(value) => bson_to_id(value).into_iter().collect(),
+            None => Vec::new(),
+        }
+        .into_iter()
+        .filter(|v| !v.is_empty() && v != "unknown")
+        .collect()
+    }
+}
+
+impl GraphableSource for MappedDocument {
+    fn get_entity_id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn get_entity_label(&self) -> Label {
+        self.mapping.label.clone()
+    }
+
+    fn get_entity_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn get_metadata_as_map(&self) -> serde_json::Map<String, Value> {
+        let mut map = serde_json::Map::new();
+        map.insert("name".to_string(), Value::String(self.name.clone()));
+        for field in &self.mapping.properties {
+            if let Some(value) = self.doc.get(field) {
+                map.insert(field.clone(), bson_to_json(value.clone()));
+            }
+        }
+        if let Ok(oid) = self.doc.get_object_id("_id") {
+            map.insert("original_oid".to_string(), Value::String(oid.to_hex()));
+        }
+        map
+    }
+
+    fn get_rich_text(&self) -> String {
+        self.mapping
+            .rich_text_fields
+            .iter()
+            .filter_map(|field| self.doc.get_str(field).ok())
+            .map(str::trim)
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    fn get_edges(&self) -> Vec<GraphEdge> {
+        let mut edges = Vec::new();
+
+        for rule in &self.mapping.edges {
+            for value in self.edge_values(&rule.field) {
+                let target_id = if value.starts_with(&rule.prefix) {
+                    value
+                } else {
+                    format!("{}{}", rule.prefix, value)
+                };
+
+                let edge = match rule.direction {
+                    EdgeDirection::Outgoing => GraphEdge {
+                        source_id: self.id.clone(),
+                        source_label: self.mapping.label.clone(),
+                        target_id,
+                        relation_type: rule.relation,
+                        target_label: rule.target.clone(),
+                        source_name: None,
+                        target_name: None,
+                        properties: serde_json::Map::new(),
+                    },
+                    EdgeDirection::Incoming => GraphEdge {
+                        source_id: target_id,
+                        source_label: rule.target.clone(),
+                        target_id: self.id.clone(),
+                        relation_type: rule.relation,
+                        target_label: self.mapping.label.clone(),
+                        source_name: None,
+                        target_name: None,
+                        properties: serde_json::Map::new(),
+                    },
+                };
+                edges.push(edge);
+            }
+        }
+
+        edges
+    }
+}
+
+fn bson_to_id(value: &Bson) -> Option<String> {
+    match value {
+        Bson::String(s) => Some(s.trim().to_string()),
+        Bson::Int32(i) => Some(i.to_string()),
+        Bson::Int64(i) => Some(i.to_string()),
+        Bson::ObjectId(oid) => Some(oid.to_hex()),
+        _ => None,
+    }
+}
+
+fn bson_to_json(value: Bson) -> Value {
+    match value {
+        Bson::ObjectId(oid) => Value::String(oid.to_hex()),
+        Bson::DateTime(dt) => Value::String(dt.try_to_rfc3339_string().unwrap_or_default()),
+        other => other.into_relaxed_extjson(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mongodb::bson::{doc, oid::ObjectId};
+
+    const SAMPLE: &str = r#"
+        [collections.droids_raw]
+        label = "Droid"
+        rich_text_fields = ["wiki_description"]
+        properties = ["model"]
+
+        [[collections.droids_raw.edges]]
+        field = "film_ids"
+        relation = "APPEARED_IN"
+        target = "Film"
+        prefix = "film_"
+
+        [[collections.droids_raw.edges]]
+        field = "owner_ids"
+        relation = "PILOTS"
+        target = "Character"
+        prefix = "char_"
+        direction = "incoming"
+    "#;
+
+    #[test]
+    fn parses_sample_and_registers_labels() {
+        let config = MappingConfig::from_toml(SAMPLE).unwrap();
+
+        let mapping = config.get("droids_raw").unwrap();
+        assert_eq!(mapping.label.as_str(), "Droid");
+        assert_eq!(mapping.id_field, "id");
+        assert_eq!(mapping.edges.len(), 2);
+        assert!(config.labels.parse("Droid").is_ok());
+        assert!(config.labels.parse("Film").is_ok());
+        // El registro es del mapping: uno vacío no conoce el label declarado
+        assert!(LabelRegistry::default().parse("Droid").is_err());
+    }
+
+    #[test]
+    fn decodes_document_with_edges() {
+        let config = MappingConfig::from_toml(SAMPLE).unwrap();
+        let oid = ObjectId::new();
+        let doc = doc! {
+            "_id": oid,
+            "id": "droid_1",
+            "name": "R2-D2",
+            "model": "R2",
+            "wiki_description": "  Astromech.  ",
+            "film_ids": ["1", "film_2", "unknown"],
+            "owner_ids": "char_5",
+        };
+
+        let droid = MappedDocument::new(config.get("droids_raw").unwrap(), doc).unwrap();
+        assert_eq!(droid.get_entity_id(), "droid_1");
+        assert_eq!(droid.get_entity_name(), "R2-D2");
+        assert_eq!(droid.get_rich_text(), "Astromech.");
+
+        let metadata = droid.get_metadata_as_map();
+        assert_eq!(metadata["model"], "R2");
+        assert_eq!(metadata["original_oid"], oid.to_hex());
+
+        let edges: Vec<(String, &str, String)> = droid
+            .get_edges()
+            .into_iter()
+            .map(|e| (e.source_id, e.relation_type.as_str(), e.target_id))
+            .collect();
+        assert_eq!(
+            edges,
+            vec![
+                ("droid_1".to_string(), "APPEARED_IN", "film_1".to_string()),
+                ("droid_1".to_string(), "APPEARED_IN", "film_2".to_string()),
+                ("char_5".to_string(), "PILOTS", "droid_1".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_edge_target() {
+        let toml = r#"
+            [collections.droids_raw]
+            label = "Droid"
+
+            [[collections.droids_raw.edges]]
+            field = "ship_ids"
+            relation = "PILOTS"
+            target = "Spaceship"
+        "#;
+        assert!(MappingConfig::from_toml(toml).is_err());
+    }
+
+    #[test]
+    fn rejects_typed_collection() {
+        let toml = r#"
+            [collections.characters_raw]
+            label = "Droid"
+        "#;
+        let err = MappingConfig::from_toml(toml).unwrap_err();
+        assert!(err.to_string().contains("characters_raw"));
+    }
+}
@@ -1,728 +1,591 @@
+use crate::cypher::is_valid_identifier;
+use anyhow::Result;
+use chrono::NaiveDate;
+use graph_derive::GraphableSource;
 use mongodb::bson::oid::ObjectId;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::{borrow::Cow, collections::BTreeSet, fmt};
+
+// Labels que producen las implementaciones de GraphableSource
+pub const ENTITY_LABELS: [&str; 6] = [
+    "Character",
+    "Film",
+    "Planet",
+    "Species",
+    "Starship",
+    "Vehicle",
+];
+// Nodos extraídos de campos de texto separados por comas (ver normalize::split_attribute)
+pub const ATTRIBUTE_LABELS: [&str; 6] = [
+    "Climate",
+    "Terrain",
+    "SkinColor",
+    "Manufacturer",
+    "StarshipClass",
+    "Person",
+];
+// Colecciones con tipo en Rust (services::decoder_for); el mapping no puede redefinirlas
+pub const TYPED_COLLECTIONS: [&str; 6] = [
+    "characters_raw",
+    "movies_raw",
+    "planets_raw",
+    "species_raw",
+    "starships_raw",
+    "vehicles_raw",
+];
+// Label común a toda entidad ingerida (índice vectorial compartido)
+pub const ENTITY_LABEL: &str = "Entity";
+pub const CHUNK_LABEL: &str = "Chunk";
 
 pub trait GraphableSource {
     fn get_entity_id(&self) -> String;
-    fn get_entity_label(&self) -> String;
+    fn get_entity_label(&self) -> Label;
     fn get_entity_name(&self) -> String;
     fn get_metadata_as_map(&self) -> serde_json::Map<String, Value>;
     fn get_rich_text(&self) -> String;
     fn get_edges(&self) -> Vec<GraphEdge>;
+    // Propiedades que se guardan como `date` de Neo4j (JSON no tiene tipo fecha)
+    fn get_date_properties(&self) -> Vec<(String, Option<NaiveDate>)> {
+        Vec::new()
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+// Documentos decodificados en runtime (tipado o mapping) comparten un mismo tipo
+impl<T: GraphableSource + ?Sized> GraphableSource for Box<T> {
+    fn get_entity_id(&self) -> String {
+        (**self).get_entity_id()
+    }
+    fn get_entity_label(&self) -> Label {
+        (**self).get_entity_label()
+    }
+    fn get_entity_name(&self) -> String {
+        (**self).get_entity_name()
+    }
+    fn get_metadata_as_map(&self) -> serde_json::Map<String, Value> {
+        (**self).get_metadata_as_map()
+    }
+    fn get_rich_text(&self) -> String {
+        (**self).get_rich_text()
+    }
+    fn get_edges(&self) -> Vec<GraphEdge> {
+        (**self).get_edges()
+    }
+    fn get_date_properties(&self) -> Vec<(String, Option<NaiveDate>)> {
+        (**self).get_date_properties()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, GraphableSource)]
+#[graph(label = "Character")]
 pub struct CharacterRaw {
     #[serde(rename = "_id")]
+    #[graph(oid)]
     pub _id: ObjectId,
-    pub id: String,                // "char_1"
+    #[graph(id)]
+    pub id: String, // "char_1"
     pub original_swapi_id: String, // "1"
-    pub name: String,              // "Luke Skywalker"
+    #[graph(name)]
+    pub name: String, // "Luke Skywalker"
+    #[graph(rich_text)]
     pub wiki_description: String,
     pub birth_year: String,
     pub gender: String,
+    #[graph(number(unit = "cm"))]
     pub height: String,
+    #[graph(number(unit = "kg"))]
     pub mass: String,
+    #[graph(edge(relation = "BORN_ON", target = "Planet", prefix = "planet_"))]
     pub homeworld_id: String,
+    #[graph(edge(relation = "BELONGS_TO", target = "Species", prefix = "species_"))]
     pub species_ids: Vec<String>,
     pub source: String,
     #[serde(default)]
+    #[graph(
+        skip,
+        edge(relation = "APPEARED_IN", target = "Film", prefix = "film_")
+    )]
     pub film_ids: Vec<String>,
     #[serde(default)]
+    #[graph(
+        skip,
+        edge(relation = "PILOTS", target = "Starship", prefix = "starship_")
+    )]
     pub starship_ids: Vec<String>,
     #[serde(default)]
+    #[graph(
+        skip,
+        edge(relation = "PILOTS", target = "Vehicle", prefix = "vehicle_")
+    )]
     pub vehicle_ids: Vec<String>,
 }
 
-impl GraphableSource for CharacterRaw {
-    fn get_entity_id(&self) -> String {
-        self.id.clone()
-    }
-
-    fn get_entity_label(&self) -> String {
-        "Character".to_string()
-    }
-
-    fn get_entity_name(&self) -> String {
-        self.name.clone()
-    }
-
-    fn get_metadata_as_map(&self) -> serde_json::Map<String, Value> {
-        let mut map = serde_json::Map::new();
-        map.insert("name".to_string(), Value::String(self.name.clone()));
-        map.insert(
-            "birth_year".to_string(),
-            Value::String(self.birth_year.clone()),
-        );
-        map.insert("gender".to_string(), Value::String(self.gender.clone()));
-        map.insert("height".to_string(), Value::String(self.height.clone()));
-        map.insert("mass".to_string(), Value::String(self.mass.clone()));
-        map.insert(
-            "homeworld_id".to_string(),
-            Value::String(self.homeworld_id.clone()),
-        );
-        map.insert(
-            "species_ids".to_string(),
-            Value::Array(
-                self.species_ids
-                    .iter()
-                    .cloned()
-                    .map(Value::String)
-                    .collect(),
-            ),
-        );
-        map.insert("source".to_string(), Value::String(self.source.clone()));
-        map.insert(
-            "original_swapi_id".to_string(),
-            Value::String(self.original_swapi_id.clone()),
-        );
-        map.insert("original_oid".to_string(), Value::String(self._id.to_hex()));
-        map
-    }
-
-    fn get_rich_text(&self) -> String {
-        self.wiki_description.clone()
-    }
-
-    fn get_edges(&self) -> Vec<GraphEdge> {
-        let mut edges = Vec::new();
-        let source_id = self.id.clone(); // Ej: "char_1"
-
-        // 1. PLANET (BORN_ON)
-        if !self.homeworld_id.is_empty() && self.homeworld_id != "unknown" {
-            edges.push(GraphEdge {
-                source_id: source_id.clone(),
-                source_label: "Character".to_string(),
-                target_id: format!("planet_{}", self.homeworld_id),
-                relation_type: RelationType::BornOn.as_str().to_string(),
-                target_label: "Planet".to_string(),
-            });
-        }
-
-        // 2. SPECIES (BELONGS_TO)
-        for species_id in &self.species_ids {
-            edges.push(GraphEdge {
-                source_id: source_id.clone(),
-                source_label: "Character".to_string(),
-                target_id: format!("species_{}", species_id),
-                relation_type: RelationType::BelongsTo.as_str().to_string(),
-                target_label: "Species".to_string(),
-            });
-        }
-
-        // --- LOS PUENTES QUE FALTABAN ---
-
-        // 3. FILMS (APPEARED_IN)
-        for film_id in &self.film_ids {
-            edges.push(GraphEdge {
-                source_id: source_id.clone(),
-                source_label: "Character".to_string(),
-                target_id: format!("film_{}", film_id), // Prefijo film_
-                relation_type: RelationType::AppearedIn.as_str().to_string(),
-                target_label: "Film".to_string(),
-            });
-        }
-
-        // 4. STARSHIPS (PILOTS)
-        for ship_id in &self.starship_ids {
-            edges.push(GraphEdge {
-                source_id: source_id.clone(),
-                source_label: "Character".to_string(),
-                target_id: format!("starship_{}", ship_id), // Prefijo starship_
-                relation_type: RelationType::Pilots.as_str().to_string(),
-                target_label: "Starship".to_string(),
-            });
-        }
-
-        for vehicle_id in &self.vehicle_ids {
-            edges.push(GraphEdge {
-                source_id: source_id.clone(),
-                source_label: "Character".to_string(),
-                target_id: format!("vehicle_{}", vehicle_id),
-                relation_type: RelationType::Pilots.as_str().to_string(),
-                target_label: "Vehicle".to_string(),
-            });
-        }
-
-        edges
-    }
-}
-
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, GraphableSource)]
+#[graph(label = "Film")]
 pub struct MoviesRaw {
     #[serde(rename = "_id")]
+    #[graph(oid)]
     pub _id: ObjectId,
+    #[graph(id)]
     pub id: String,
+    #[graph(name)]
     pub title: String,
     pub episode_id: i32,
+    // Las personas no dependen de SPLIT_ATTRIBUTES: sin ellas se pierden DIRECTED/PRODUCED
+    #[graph(split(
+        label = "Person",
+        relation = "DIRECTED",
+        prefix = "person_",
+        direction = "incoming",
+        always
+    ))]
     pub director: String,
+    // "Gary Kurtz, Rick McCallum"; documentos viejos no lo traen
+    #[serde(default)]
+    #[graph(split(
+        label = "Person",
+        relation = "PRODUCED",
+        prefix = "person_",
+        direction = "incoming",
+        always
+    ))]
+    pub producer: String,
+    #[graph(date)]
     pub release_date: String,
     pub opening_crawl: String,
+    #[graph(rich_text)]
     pub wiki_plot: String,
+    // Personajes -> película
+    #[graph(edge(
+        relation = "APPEARED_IN",
+        target = "Character",
+        prefix = "char_",
+        direction = "incoming"
+    ))]
     pub character_ids: Vec<String>,
     pub source: String,
 }
 
-impl GraphableSource for MoviesRaw {
-    fn get_entity_id(&self) -> String {
-        self.id.clone()
-    }
-
-    fn get_entity_label(&self) -> String {
-        "Film".to_string()
-    }
-
-    fn get_entity_name(&self) -> String {
-        self.title.clone()
-    }
-
-    fn get_metadata_as_map(&self) -> serde_json::Map<String, Value> {
-        let mut map = serde_json::Map::new();
-        map.insert("title".to_string(), Value::String(self.title.clone()));
-        map.insert(
-            "episode_id".to_string(),
-            Value::Number(self.episode_id.into()),
-        );
-        map.insert("director".to_string(), Value::String(self.director.clone()));
-        map.insert(
-            "release_date".to_string(),
-            Value::String(self.release_date.clone()),
-        );
-        map.insert(
-            "opening_crawl".to_string(),
-            Value::String(self.opening_crawl.clone()),
-        );
-        map.insert(
-            "character_ids".to_string(),
-            Value::Array(
-                self.character_ids
-                    .iter()
-                    .cloned()
-                    .map(Value::String)
-                    .collect(),
-            ),
-        );
-        map.insert("source".to_string(), Value::String(self.source.clone()));
-        map.insert("original_oid".to_string(), Value::String(self._id.to_hex()));
-        map
-    }
-
-    fn get_rich_text(&self) -> String {
-        self.wiki_plot.clone()
-    }
-
-    fn get_edges(&self) -> Vec<GraphEdge> {
-        let mut edges = Vec::new();
-        let film_target_id = self.id.clone(); // Ej: "film_1"
-
-        // 1. Relación: PERSONAJES -> PELÍCULA
-        for char_raw_id in &self.character_ids {
-            let char_node_id = format!("char_{}", char_raw_id);
-            edges.push(GraphEdge {
-                source_id: char_node_id,
-                source_label: "Character".to_string(),
-                target_id: film_target_id.clone(),
-                relation_type: RelationType::AppearedIn.as_str().to_string(),
-                target_label: "Film".to_string(),
-            });
-        }
-
-        edges
-    }
-}
-
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, GraphableSource)]
+#[graph(label = "Planet")]
 pub struct PlanetRaw {
     #[serde(rename = "_id")]
+    #[graph(oid)]
     pub _id: ObjectId,
+    #[graph(id)]
     pub id: String,
     pub original_swapi_id: String,
+    #[graph(name)]
     pub name: String,
     pub rotation_period: String,
     pub orbital_period: String,
+    #[graph(number(unit = "km"))]
     pub diameter: String,
+    #[graph(split(label = "Climate", relation = "HAS_CLIMATE", prefix = "climate_"))]
     pub climate: String,
     pub gravity: String,
+    #[graph(split(label = "Terrain", relation = "HAS_TERRAIN", prefix = "terrain_"))]
     pub terrain: String,
     pub surface_water: String,
+    #[graph(number)]
     pub population: String,
+    #[graph(rich_text)]
     pub wiki_description: String,
+    #[graph(edge(relation = "APPEARED_IN", target = "Film", prefix = "film_"))]
     pub film_ids: Vec<String>,
     pub resident_ids: Vec<String>,
     pub source: String,
 }
 
-impl GraphableSource for PlanetRaw {
-    fn get_entity_id(&self) -> String {
-        self.id.clone()
-    }
-
-    fn get_entity_label(&self) -> String {
-        "Planet".to_string()
-    }
-
-    fn get_entity_name(&self) -> String {
-        self.name.clone()
-    }
-
-    fn get_metadata_as_map(&self) -> serde_json::Map<String, Value> {
-        let mut map = serde_json::Map::new();
-        map.insert("name".to_string(), Value::String(self.name.clone()));
-        map.insert(
-            "rotation_period".to_string(),
-            Value::String(self.rotation_period.clone()),
-        );
-        map.insert(
-            "orbital_period".to_string(),
-            Value::String(self.orbital_period.clone()),
-        );
-        map.insert("diameter".to_string(), Value::String(self.diameter.clone()));
-        map.insert("climate".to_string(), Value::String(self.climate.clone()));
-        map.insert("gravity".to_string(), Value::String(self.gravity.clone()));
-        map.insert("terrain".to_string(), Value::String(self.terrain.clone()));
-        map.insert(
-            "surface_water".to_string(),
-            Value::String(self.surface_water.clone()),
-        );
-        map.insert(
-            "population".to_string(),
-            Value::String(self.population.clone()),
-        );
-        map.insert(
-            "film_ids".to_string(),
-            Value::Array(self.film_ids.iter().cloned().map(Value::String).collect()),
-        );
-        map.insert(
-            "resident_ids".to_string(),
-            Value::Array(
-                self.resident_ids
-                    .iter()
-                    .cloned()
-                    .map(Value::String)
-                    .collect(),
-            ),
-        );
-        map.insert("source".to_string(), Value::String(self.source.clone()));
-        map.insert(
-            "original_swapi_id".to_string(),
-            Value::String(self.original_swapi_id.clone()),
-        );
-        map.insert("original_oid".to_string(), Value::String(self._id.to_hex()));
-        map
-    }
-
-    fn get_rich_text(&self) -> String {
-        self.wiki_description.clone()
-    }
-
-    fn get_edges(&self) -> Vec<GraphEdge> {
-        let mut edges = Vec::new();
-        let source_id = self.id.clone(); // "planet_X"
-
-        for film_id in &self.film_ids {
-            edges.push(GraphEdge {
-                source_id: source_id.clone(),
-                source_label: "Planet".to_string(),
-                target_id: format!("film_{}", film_id), // <--- FIX: Prefijo film_
-                relation_type: RelationType::AppearedIn.as_str().to_string(),
-                target_label: "Film".to_string(),
-            });
-        }
-
-        edges
-    }
-}
-
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, GraphableSource)]
+#[graph(label = "Species")]
 pub struct SpeciesRaw {
     #[serde(rename = "_id")]
+    #[graph(oid)]
     pub _id: ObjectId,
+    #[graph(id)]
     pub id: String,
     pub original_swapi_id: String,
+    #[graph(name)]
     pub name: String,
     pub classification: Option<String>,
     pub designation: String,
     pub average_height: String,
     pub average_lifespan: String,
     pub language: String,
+    #[graph(split(
+        label = "SkinColor",
+        relation = "HAS_SKIN_COLOR",
+        prefix = "skin_color_"
+    ))]
     pub skin_colors: String,
+    #[graph(rich_text)]
     pub wiki_description: String,
+    #[graph(edge(relation = "RESIDENT_OF", target = "Planet", prefix = "planet_"))]
     pub homeworld_id: Option<String>,
     pub people_ids: Vec<String>,
+    #[graph(edge(relation = "APPEARED_IN", target = "Film", prefix = "film_"))]
     pub film_ids: Vec<String>,
     pub source: String,
 }
 
-impl GraphableSource for SpeciesRaw {
-    fn get_entity_id(&self) -> String {
-        self.id.clone()
-    }
-
-    fn get_entity_label(&self) -> String {
-        "Species".to_string()
-    }
-
-    fn get_entity_name(&self) -> String {
-        self.name.clone()
-    }
-
-    fn get_metadata_as_map(&self) -> serde_json::Map<String, Value> {
-        let mut map = serde_json::Map::new();
-
-        map.insert("name".to_string(), Value::String(self.name.clone()));
-        map.insert(
-            "designation".to_string(),
-            Value::String(self.designation.clone()),
-        );
-        map.insert(
-            "average_height".to_string(),
-            Value::String(self.average_height.clone()),
-        );
-        map.insert(
-            "average_lifespan".to_string(),
-            Value::String(self.average_lifespan.clone()),
-        );
-        map.insert("language".to_string(), Value::String(self.language.clone()));
-        map.insert(
-            "skin_colors".to_string(),
-            Value::String(self.skin_colors.clone()),
-        );
-        map.insert("source".to_string(), Value::String(self.source.clone()));
-        map.insert(
-            "original_swapi_id".to_string(),
-            Value::String(self.original_swapi_id.clone()),
-        );
-        map.insert("original_oid".to_string(), Value::String(self._id.to_hex()));
-
-        map.insert(
-            "people_ids".to_string(),
-            Value::Array(self.people_ids.iter().cloned().map(Value::String).collect()),
-        );
-        map.insert(
-            "film_ids".to_string(),
-            Value::Array(self.film_ids.iter().cloned().map(Value::String).collect()),
-        );
-
-        map.insert(
-            "classification".to_string(),
-            Value::String(
-                self.classification
-                    .clone()
-                    .unwrap_or_else(|| "unknown".to_string()),
-            ),
-        );
-
-        map.insert(
-            "homeworld_id".to_string(),
-            Value::String(
-                self.homeworld_id
-                    .clone()
-                    .unwrap_or_else(|| "unknown".to_string()),
-            ),
-        );
-
-        map
-    }
-
-    fn get_rich_text(&self) -> String {
-        self.wiki_description.clone()
-    }
-
-    fn get_edges(&self) -> Vec<GraphEdge> {
-        let mut edges = Vec::new();
-        let source_id = self.id.clone(); // "species_X"
-
-        if let Some(homeworld) = &self.homeworld_id {
-            if homeworld != "unknown" {
-                edges.push(GraphEdge {
-                    source_id: source_id.clone(),
-                    source_label: "Species".to_string(),
-                    target_id: format!("planet_{}", homeworld), // <--- FIX: Prefijo planet_
-                    relation_type: RelationType::ResidentOf.as_str().to_string(),
-                    target_label: "Planet".to_string(),
-                });
-            }
-        }
-
-        // Relación: APPEARED_IN (Species -> Film)
-        for film_id in &self.film_ids {
-            edges.push(GraphEdge {
-                source_id: source_id.clone(),
-                source_label: "Species".to_string(),
-                target_id: format!("film_{}", film_id), // <--- FIX: Prefijo film_
-                relation_type: RelationType::AppearedIn.as_str().to_string(),
-                target_label: "Film".to_string(),
-            });
-        }
-
-        edges
-    }
-}
-
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, GraphableSource)]
+#[graph(label = "Starship")]
 pub struct StarshipRaw {
     #[serde(rename = "_id")]
+    #[graph(oid)]
     pub _id: ObjectId,
+    #[graph(id)]
     pub id: String,
     pub original_swapi_id: String,
+    #[graph(name)]
     pub name: String,
     pub model: String,
+    #[graph(split(
+        label = "Manufacturer",
+        relation = "MANUFACTURED_BY",
+        prefix = "manufacturer_"
+    ))]
     pub manufacturer: String,
+    #[graph(rich_text)]
     pub wiki_description: String,
+    #[graph(number(unit = "credits"))]
     pub cost_in_credits: String,
     pub length: String,
     pub max_atmosphering_speed: String,
+    #[graph(range)]
     pub crew: String,
     pub passengers: String,
     pub cargo_capacity: String,
+    #[graph(number)]
     pub hyperdrive_rating: String,
+    #[graph(split(
+        label = "StarshipClass",
+        relation = "HAS_STARSHIP_CLASS",
+        prefix = "starship_class_"
+    ))]
     pub starship_class: String,
+    #[graph(edge(
+        relation = "PILOTS",
+        target = "Character",
+        prefix = "char_",
+        direction = "incoming"
+    ))]
     pub pilot_ids: Vec<String>,
+    #[graph(edge(relation = "APPEARED_IN", target = "Film", prefix = "film_"))]
     pub film_ids: Vec<String>,
     pub source: String,
 }
 
-impl GraphableSource for StarshipRaw {
-    fn get_entity_id(&self) -> String {
-        self.id.clone()
-    }
-
-    fn get_entity_label(&self) -> String {
-        "Starship".to_string()
-    }
-
-    fn get_entity_name(&self) -> String {
-        self.name.clone()
-    }
-
-    fn get_metadata_as_map(&self) -> serde_json::Map<String, Value> {
-        let mut map = serde_json::Map::new();
-        map.insert("name".to_string(), Value::String(self.name.clone()));
-        map.insert("model".to_string(), Value::String(self.model.clone()));
-        map.insert(
-            "manufacturer".to_string(),
-            Value::String(self.manufacturer.clone()),
-        );
-        map.insert(
-            "cost_in_credits".to_string(),
-            Value::String(self.cost_in_credits.clone()),
-        );
-        map.insert("length".to_string(), Value::String(self.length.clone()));
-        map.insert(
-            "max_atmosphering_speed".to_string(),
-            Value::String(self.max_atmosphering_speed.clone()),
-        );
-        map.insert("crew".to_string(), Value::String(self.crew.clone()));
-        map.insert(
-            "passengers".to_string(),
-            Value::String(self.passengers.clone()),
-        );
-        map.insert(
-            "cargo_capacity".to_string(),
-            Value::String(self.cargo_capacity.clone()),
-        );
-        map.insert(
-            "hyperdrive_rating".to_string(),
-            Value::String(self.hyperdrive_rating.clone()),
-        );
-        map.insert(
-            "starship_class".to_string(),
-            Value::String(self.starship_class.clone()),
-        );
-        map.insert(
-            "pilot_ids".to_string(),
-            Value::Array(self.pilot_ids.iter().cloned().map(Value::String).collect()),
-        );
-        map.insert(
-            "film_ids".to_string(),
-            Value::Array(self.film_ids.iter().cloned().map(Value::String).collect()),
-        );
-        map.insert("source".to_string(), Value::String(self.source.clone()));
-        map.insert(
-            "original_swapi_id".to_string(),
-            Value::String(self.original_swapi_id.clone()),
-        );
-        map.insert("original_oid".to_string(), Value::String(self._id.to_hex()));
-        map
-    }
-
-    fn get_rich_text(&self) -> String {
-        self.wiki_description.clone()
-    }
-
-    fn get_edges(&self) -> Vec<GraphEdge> {
-        let mut edges = Vec::new();
-        let ship_id = self.id.clone(); // "starship_X"
-
-        // Relación: PILOTS (Character -> Starship)
-        for pilot_id in &self.pilot_ids {
-            edges.push(GraphEdge {
-                source_id: format!("char_{}", pilot_id), // <--- FIX: El piloto es un char_
-                source_label: "Character".to_string(),
-                target_id: ship_id.clone(),
-                relation_type: RelationType::Pilots.as_str().to_string(),
-                target_label: "Starship".to_string(),
-            });
-        }
-
-        // Relación: APPEARED_IN (Starship -> Film)
-        for film_id in &self.film_ids {
-            edges.push(GraphEdge {
-                source_id: ship_id.clone(),
-                source_label: "Starship".to_string(),
-                target_id: format!("film_{}", film_id), // <--- FIX: Prefijo film_
-                relation_type: RelationType::AppearedIn.as_str().to_string(),
-                target_label: "Film".to_string(),
-            });
-        }
-
-        edges
-    }
-}
-
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, GraphableSource)]
+#[graph(label = "Vehicle")]
 pub struct VehicleRaw {
     #[serde(rename = "_id")]
+    #[graph(oid)]
     pub _id: ObjectId,
+    #[graph(id)]
     pub id: String,
     pub original_swapi_id: String,
+    #[graph(name)]
     pub name: String,
     pub model: String,
+    #[graph(split(
+        label = "Manufacturer",
+        relation = "MANUFACTURED_BY",
+        prefix = "manufacturer_"
+    ))]
     pub manufacturer: String,
+    #[graph(rich_text)]
     pub wiki_description: String,
+    #[graph(number(unit = "credits"))]
     pub cost_in_credits: String,
     pub length: String,
     pub max_atmosphering_speed: String,
+    #[graph(range)]
     pub crew: String,
     pub passengers: String,
     pub cargo_capacity: String,
     pub vehicle_class: String,
+    #[graph(edge(
+        relation = "PILOTS",
+        target = "Character",
+        prefix = "char_",
+        direction = "incoming"
+    ))]
     pub pilot_ids: Vec<String>,
+    #[graph(edge(relation = "APPEARED_IN", target = "Film", prefix = "film_"))]
     pub film_ids: Vec<String>,
     pub source: String,
 }
 
-impl GraphableSource for VehicleRaw {
-    fn get_entity_id(&self) -> String {
-        self.id.clone()
+// --- Soporte para el código generado por #[derive(GraphableSource)] ---
+
+// Cada campo se guarda con su tipo serde: números y listas no pasan a string
+pub fn to_property<T: Serialize>(value: &T) -> Value {
+    serde_json::to_value(value).unwrap_or(Value::Null)
+}
+
+pub fn join_rich_text(parts: &[&str]) -> String {
+    parts
+        .iter()
+        .map(|part| part.trim())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+// Ids referenciados por un campo de edge; vacíos y "unknown" no generan relación
+pub trait EdgeIds {
+    fn edge_ids(&self) -> Vec<&str>;
+}
+
+fn is_edge_id(id: &str) -> bool {
+    !id.is_empty() && id != "unknown"
+}
+
+impl EdgeIds for String {
+    fn edge_ids(&self) -> Vec<&str> {
+        [self.as_str()]
+            .into_iter()
+            .filter(|id| is_edge_id(id))
+            .collect()
     }
+}
 
-    fn get_entity_label(&self) -> String {
-        "Vehicle".to_string()
+impl EdgeIds for Option<String> {
+    fn edge_ids(&self) -> Vec<&str> {
+        self.as_deref()
+            .into_iter()
+            .filter(|id| is_edge_id(id))
+            .collect()
     }
+}
 
-    fn get_entity_name(&self) -> String {
-        self.name.clone()
+impl EdgeIds for Vec<String> {
+    fn edge_ids(&self) -> Vec<&str> {
+        self.iter()
+            .map(String::as_str)
+            .filter(|id| is_edge_id(id))
+            .collect()
     }
+}
 
-    fn get_metadata_as_map(&self) -> serde_json::Map<String, Value> {
-        let mut map = serde_json::Map::new();
-        map.insert("name".to_string(), Value::String(self.name.clone()));
-        map.insert("model".to_string(), Value::String(self.model.clone()));
-        map.insert(
-            "manufacturer".to_string(),
-            Value::String(self.manufacturer.clone()),
-        );
-        map.insert(
-            "cost_in_credits".to_string(),
-            Value::String(self.cost_in_credits.clone()),
-        );
-        map.insert("length".to_string(), Value::String(self.length.clone()));
-        map.insert(
-            "max_atmosphering_speed".to_string(),
-            Value::String(self.max_atmosphering_speed.clone()),
-        );
-        map.insert("crew".to_string(), Value::String(self.crew.clone()));
-        map.insert(
-            "passengers".to_string(),
-            Value::String(self.passengers.clone()),
-        );
-        map.insert(
-            "cargo_capacity".to_string(),
-            Value::String(self.cargo_capacity.clone()),
-        );
-        map.insert(
-            "vehicle_class".to_string(),
-            Value::String(self.vehicle_class.clone()),
-        );
-        map.insert(
-            "pilot_ids".to_string(),
-            Value::Array(self.pilot_ids.iter().cloned().map(Value::String).collect()),
-        );
-        map.insert(
-            "film_ids".to_string(),
-            Value::Array(self.film_ids.iter().cloned().map(Value::String).collect()),
-        );
-        map.insert("source".to_string(), Value::String(self.source.clone()));
-        map.insert(
-            "original_swapi_id".to_string(),
-            Value::String(self.original_swapi_id.clone()),
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GraphEdge {
+    pub source_id: String,
+    pub source_label: Label,
+    pub target_id: String,
+    pub relation_type: RelationType,
+    pub target_label: Label,
+    // Nombre para nodos que no tienen documento propio (atributos, personas)
+    #[serde(default)]
+    pub source_name: Option<String>,
+    #[serde(default)]
+    pub target_name: Option<String>,
+    // El writer agrega la procedencia (source_collection, source_oid, asserted_by)
+    #[serde(default)]
+    pub properties: serde_json::Map<String, Value>,
+}
+
+// Label de nodo ya validado: solo se construye desde el derive o con un
+// `LabelRegistry`, así que es seguro interpolarlo en Cypher.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Label(Cow<'static, str>);
+
+impl Label {
+    // Solo para labels fijos en el código (lo usa el derive). Al compilar el derive
+    // solo comprueba que sea un identificador; la allowlist se verifica en debug.
+    pub fn from_static(name: &'static str) -> Label {
+        debug_assert!(
+            ENTITY_LABELS.contains(&name) || ATTRIBUTE_LABELS.contains(&name),
+            "Label fijo fuera de la allowlist: {name}"
         );
-        map.insert("original_oid".to_string(), Value::String(self._id.to_hex()));
-        map
+        Label(Cow::Borrowed(name))
     }
 
-    fn get_rich_text(&self) -> String {
-        self.wiki_description.clone()
+    pub fn as_str(&self) -> &str {
+        &self.0
     }
+}
 
-    fn get_edges(&self) -> Vec<GraphEdge> {
-        let mut edges = Vec::new();
-        let ship_id = self.id.clone(); // "Vehicle_X"
-
-        // Relación: PILOTS (Character -> Vehicle)
-        for pilot_id in &self.pilot_ids {
-            edges.push(GraphEdge {
-                source_id: format!("char_{}", pilot_id), // <--- FIX: El piloto es un char_
-                source_label: "Character".to_string(),
-                target_id: ship_id.clone(),
-                relation_type: RelationType::Pilots.as_str().to_string(),
-                target_label: "Vehicle".to_string(),
-            });
-        }
+impl fmt::Display for Label {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
 
-        // Relación: APPEARED_IN (Vehicle -> Film)
-        for film_id in &self.film_ids {
-            edges.push(GraphEdge {
-                source_id: ship_id.clone(),
-                source_label: "Vehicle".to_string(),
-                target_id: format!("film_{}", film_id), // <--- FIX: Prefijo film_
-                relation_type: RelationType::AppearedIn.as_str().to_string(),
-                target_label: "Film".to_string(),
-            });
-        }
+// Sin mapping a mano, serde solo acepta los labels de los tipos en Rust
+impl TryFrom<String> for Label {
+    type Error = anyhow::Error;
 
-        edges
+    fn try_from(value: String) -> Result<Self> {
+        LabelRegistry::default().parse(&value)
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct GraphEdge {
-    pub source_id: String,
-    pub source_label: String,
-    pub target_id: String,
-    pub relation_type: String,
-    pub target_label: String,
+impl From<Label> for String {
+    fn from(label: Label) -> Self {
+        label.0.into_owned()
+    }
+}
+
+// Allowlist de labels: los de los tipos en Rust y los de atributos, más los que
+// declara el mapping de colecciones (que es dueño del registro, ver mapping.rs)
+#[derive(Debug, Clone, Default)]
+pub struct LabelRegistry {
+    mapped: BTreeSet<String>,
 }
 
+impl LabelRegistry {
+    // Rechaza todo lo que no esté en la allowlist o no sea un identificador válido
+    pub fn parse(&self, name: &str) -> Result<Label> {
+        if !is_valid_identifier(name) {
+            return Err(anyhow::anyhow!("Label inválido: {:?}", name));
+        }
+        let known = self.known();
+        if !known.iter().any(|label| label == name) {
+            return Err(anyhow::anyhow!(
+                "Label desconocido: {} (válidos: {})",
+                name,
+                known.join(", ")
+            ));
+        }
+        Ok(Label(Cow::Owned(name.to_string())))
+    }
+
+    // Agrega un label a la allowlist; solo se usa al cargar el mapping de colecciones
+    pub fn register(&mut self, name: &str) -> Result<Label> {
+        if !is_valid_identifier(name) {
+            return Err(anyhow::anyhow!("Label inválido: {:?}", name));
+        }
+        if name == ENTITY_LABEL || name == CHUNK_LABEL {
+            return Err(anyhow::anyhow!("Label reservado: {}", name));
+        }
+        if !ENTITY_LABELS.contains(&name) && !ATTRIBUTE_LABELS.contains(&name) {
+            self.mapped.insert(name.to_string());
+        }
+        Ok(Label(Cow::Owned(name.to_string())))
+    }
+
+    // Labels válidos: los de los tipos en Rust, los de atributos y los del mapping
+    pub fn known(&self) -> Vec<String> {
+        ENTITY_LABELS
+            .iter()
+            .chain(ATTRIBUTE_LABELS.iter())
+            .map(|l| l.to_string())
+            .chain(self.mapped.iter().cloned())
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum RelationType {
     AppearedIn,
     BornOn,
     BelongsTo,
     Pilots,
+    Directed,
     Produced,
     ResidentOf,
+    HasClimate,
+    HasTerrain,
+    HasSkinColor,
+    ManufacturedBy,
+    HasStarshipClass,
 }
 
 impl RelationType {
-    fn as_str(&self) -> &'static str {
+    pub const ALL: [RelationType; 12] = [
+        RelationType::AppearedIn,
+        RelationType::BornOn,
+        RelationType::BelongsTo,
+        RelationType::Pilots,
+        RelationType::Directed,
+        RelationType::Produced,
+        RelationType::ResidentOf,
+        RelationType::HasClimate,
+        RelationType::HasTerrain,
+        RelationType::HasSkinColor,
+        RelationType::ManufacturedBy,
+        RelationType::HasStarshipClass,
+    ];
+
+    pub fn parse(name: &str) -> Result<RelationType> {
+        RelationType::ALL
+            .into_iter()
+            .find(|relation| relation.as_str() == name)
+            .ok_or_else(|| {
+                let valid: Vec<&str> = RelationType::ALL.iter().map(|r| r.as_str()).collect();
+                anyhow::anyhow!(
+                    "Relación no soportada: {} (válidas: {})",
+                    name,
+                    valid.join(", ")
+                )
+            })
+    }
+
+    pub const fn as_str(&self) -> &'static str {
         match self {
             RelationType::AppearedIn => "APPEARED_IN",
             RelationType::BornOn => "BORN_ON",
             RelationType::BelongsTo => "BELONGS_TO",
             RelationType::Pilots => "PILOTS",
+            RelationType::Directed => "DIRECTED",
             RelationType::Produced => "PRODUCED",
             RelationType::ResidentOf => "RESIDENT_OF",
+            RelationType::HasClimate => "HAS_CLIMATE",
+            RelationType::HasTerrain => "HAS_TERRAIN",
+            RelationType::HasSkinColor => "HAS_SKIN_COLOR",
+            RelationType::ManufacturedBy => "MANUFACTURED_BY",
+            RelationType::HasStarshipClass => "HAS_STARSHIP_CLASS",
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn label_registry_parse_only_accepts_known_labels() {
+        let mut labels = LabelRegistry::default();
+        assert_eq!(labels.parse("Character").unwrap().as_str(), "Character");
+        assert_eq!(labels.parse("Climate").unwrap().as_str(), "Climate");
+
+        assert!(labels.parse("Droid").is_err());
+        assert!(labels.parse("character").is_err());
+        assert!(labels.parse("").is_err());
+        assert!(labels.parse(" Character").is_err());
+        assert!(labels.parse("Character`").is_err());
+        assert!(labels.parse("1Character").is_err());
+        assert!(labels.parse("Dróide").is_err());
+
+        labels.register("Droid").unwrap();
+        assert_eq!(labels.parse("Droid").unwrap().as_str(), "Droid");
+    }
+
+    #[test]
+    fn label_registry_register_rejects_reserved_and_invalid_labels() {
+        let mut labels = LabelRegistry::default();
+        assert!(labels.register(ENTITY_LABEL).is_err());
+        assert!(labels.register(CHUNK_LABEL).is_err());
+        assert!(labels.register("Dro`id").is_err());
+        assert!(labels.register("").is_err());
+        assert!(labels.known().iter().all(|l| l != "Dro`id"));
+    }
+
+    #[test]
+    fn relation_type_parse_round_trips_and_rejects_unknown() {
+        for relation in RelationType::ALL {
+            assert_eq!(RelationType::parse(relation.as_str()).unwrap(), relation);
+        }
+
+        assert!(RelationType::parse("").is_err());
+        assert!(RelationType::parse("appeared_in").is_err());
+        assert!(RelationType::parse(" APPEARED_IN").is_err());
+        assert!(RelationType::parse("`APPEARED_IN`").is_err());
+        assert!(RelationType::parse("KNOWS").is_err());
+    }
+}
@@ -0,0 +1,208 @@
+use chrono::NaiveDate;
+use serde_json::{Map, Value};
+use std::{collections::BTreeMap, sync::OnceLock};
+
+// Valores que SWAPI usa para "sin dato"
+const MISSING: [&str; 6] = ["", "unknown", "n/a", "na", "none", "indefinite"];
+
+// "1,000" -> 1000, "1.5" -> 1.5, "unknown" -> None
+pub fn parse_number(raw: &str) -> Option<Value> {
+    let cleaned: String = raw
+        .trim()
+        .chars()
+        .filter(|c| *c != ',' && !c.is_whitespace())
+        .collect();
+    if MISSING.contains(&cleaned.to_lowercase().as_str()) {
+        return None;
+    }
+
+    if let Ok(i) = cleaned.parse::<i64>() {
+        return Some(Value::from(i));
+    }
+    cleaned
+        .parse::<f64>()
+        .ok()
+        .filter(|f| f.is_finite())
+        .map(Value::from)
+}
+
+// "30-165" -> (30, 165); un número suelto es un rango de un solo valor
+pub fn parse_range(raw: &str) -> Option<(Value, Value)> {
+    let trimmed = raw.trim();
+    match trimmed.split_once('-') {
+        Some((min, max)) if !min.trim().is_empty() => {
+            Some((parse_number(min)?, parse_number(max)?))
+        }
+        _ => parse_number(trimmed).map(|n| (n.clone(), n)),
+    }
+}
+
+// Guarda el valor numérico en `key`, el original en `{key}_raw` y la unidad si aplica.
+// Sin valor se escribe null, que en Neo4j borra la propiedad.
+pub fn insert_number(map: &mut Map<String, Value>, key: &str, raw: &str, unit: Option<&str>) {
+    let parsed = parse_number(raw);
+    if let Some(unit) = unit {
+        let unit = parsed.as_ref().map(|_| Value::from(unit));
+        map.insert(format!("{}_unit", key), unit.unwrap_or(Value::Null));
+    }
+    map.insert(key.to_string(), parsed.unwrap_or(Value::Null));
+    map.insert(format!("{}_raw", key), Value::from(raw));
+}
+
+// Igual que `insert_number`, más `{key}_min` / `{key}_max`. `key` solo lleva valor
+// cuando el rango es un número exacto.
+pub fn insert_range(map: &mut Map<String, Value>, key: &str, raw: &str, unit: Option<&str>) {
+    let (min, max) = match parse_range(raw) {
+        Some((min, max)) => (Some(min), Some(max)),
+        None => (None, None),
+    };
+    let exact = min.clone().filter(|min| Some(min) == max.as_ref());
+
+    if let Some(unit) = unit {
+        let unit = min.as_ref().map(|_| Value::from(unit));
+        map.insert(format!("{}_unit", key), unit.unwrap_or(Value::Null));
+    }
+    map.insert(key.to_string(), exact.unwrap_or(Value::Null));
+    map.insert(format!("{}_min", key), min.unwrap_or(Value::Null));
+    map.insert(format!("{}_max", key), max.unwrap_or(Value::Null));
+    map.insert(format!("{}_raw", key), Value::from(raw));
+}
+
+// Sufijos societarios que SWAPI separa con coma ("Gallofree Yards, Inc.")
+const NAME_SUFFIXES: [&str; 5] = ["inc", "inc.", "ltd", "ltd.", "llc"];
+
+// Labels de atributos a extraer como nodos; sin configurar se extraen todos
+static SPLIT_LABELS: OnceLock<Vec<String>> = OnceLock::new();
+
+pub fn configure_split_labels(labels: Vec<String>) {
+    if SPLIT_LABELS.set(labels).is_err() {
+        eprintln!("⚠️ Labels de atributos ya configurados, se ignora el cambio");
+    }
+}
+
+pub fn split_enabled(label: &str) -> bool {
+    SPLIT_LABELS
+        .get()
+        .is_none_or(|labels| labels.iter().any(|l| l.eq_ignore_ascii_case(label)))
+}
+
+// "temperate, Temperate,  arid" -> [("arid", "arid"), ("temperate", "temperate")]
+// La clave (slug) es el nombre normalizado y sirve para deduplicar entre documentos.
+pub fn split_attribute(raw: &str) -> Vec<(String, String)> {
+    let mut values: Vec<String> = Vec::new();
+    for part in raw.split(',') {
+        let name = part.split_whitespace().collect::<Vec<_>>().join(" ");
+        if name.is_empty() {
+            continue;
+        }
+        match values.last_mut() {
+            Some(prev) if NAME_SUFFIXES.contains(&name.to_lowercase().as_str()) => {
+                prev.push_str(", ");
+                prev.push_str(&name);
+            }
+            _ => values.push(name),
+        }
+    }
+
+    let mut unique: BTreeMap<String, String> = BTreeMap::new();
+    for name in values {
+        if MISSING.contains(&name.to_lowercase().as_str()) {
+            continue;
+        }
+        let slug = slugify(&name);
+        if !slug.is_empty() {
+            unique.entry(slug).or_insert(name);
+        }
+    }
+    unique.into_iter().collect()
+}
+
+fn slugify(name: &str) -> String {
+    name.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+// SWAPI usa ISO 8601 ("1977-05-25"); cualquier otra cosa queda sin fecha
+pub fn parse_date(raw: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(raw.trim(), "%Y-%m-%d").ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_number_handles_swapi_values() {
+        assert_eq!(parse_number("1,000"), Some(Value::from(1000)));
+        assert_eq!(parse_number(" 172 "), Some(Value::from(172)));
+        assert_eq!(parse_number("1.5"), Some(Value::from(1.5)));
+        assert_eq!(parse_number("unknown"), None);
+        assert_eq!(parse_number("n/a"), None);
+        assert_eq!(parse_number("N/A"), None);
+        assert_eq!(parse_number(""), None);
+        assert_eq!(parse_number("30-165"), None);
+    }
+
+    #[test]
+    fn parse_range_splits_min_and_max() {
+        assert_eq!(
+            parse_range("30-165"),
+            Some((Value::from(30), Value::from(165)))
+        );
+        assert_eq!(
+            parse_range("1,000"),
+            Some((Value::from(1000), Value::from(1000)))
+        );
+        // Un signo inicial no es un rango
+        assert_eq!(parse_range("-5"), Some((Value::from(-5), Value::from(-5))));
+        assert_eq!(parse_range("30-unknown"), None);
+        assert_eq!(parse_range("unknown"), None);
+    }
+
+    #[test]
+    fn split_attribute_dedups_and_joins_suffixes() {
+        assert_eq!(
+            split_attribute("temperate, Temperate,  arid"),
+            vec![
+                ("arid".to_string(), "arid".to_string()),
+                ("temperate".to_string(), "temperate".to_string()),
+            ]
+        );
+        assert_eq!(
+            split_attribute("Gallofree Yards, Inc."),
+            vec![(
+                "gallofree_yards_inc".to_string(),
+                "Gallofree Yards, Inc.".to_string()
+            )]
+        );
+        assert_eq!(
+            split_attribute("Kuat Drive Yards, Fondor Shipyards"),
+            vec![
+                (
+                    "fondor_shipyards".to_string(),
+                    "Fondor Shipyards".to_string()
+                ),
+                (
+                    "kuat_drive_yards".to_string(),
+                    "Kuat Drive Yards".to_string()
+                ),
+            ]
+        );
+        assert!(split_attribute("unknown").is_empty());
+        assert!(split_attribute("n/a, ").is_empty());
+    }
+
+    #[test]
+    fn parse_date_accepts_only_iso() {
+        assert_eq!(
+            parse_date(" 1977-05-25 "),
+            NaiveDate::from_ymd_opt(1977, 5, 25)
+        );
+        assert_eq!(parse_date("unknown"), None);
+        assert_eq!(parse_date("25/05/1977"), None);
+        assert_eq!(parse_date(""), None);
+    }
+}
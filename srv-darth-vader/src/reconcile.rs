@@ -0,0 +1,179 @@
+use crate::{
+    cypher::quote_identifier,
+    models::{ENTITY_LABEL, Label},
+};
+use anyhow::Result;
+use neo4rs::{Graph, query};
+use serde::{Deserialize, Serialize};
+
+// Muestra de ids que se devuelve en el reporte
+const SAMPLE_IDS: i64 = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReconcileMode {
+    // archived: true, el nodo queda pero fuera de búsquedas
+    Archive,
+    // DETACH DELETE del nodo y sus chunks
+    Delete,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ReconcileOptions {
+    pub mode: ReconcileMode,
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReconcileReport {
+    pub labels: Vec<String>,
+    pub stale: i64,
+    pub removed: i64,
+    pub dry_run: bool,
+    pub sample_ids: Vec<String>,
+    // Motivo por el que no se reconcilió (pasada incompleta, colección vacía)
+    pub skipped_reason: Option<String>,
+}
+
+impl ReconcileReport {
+    pub fn skipped(reason: impl ToString) -> Self {
+        Self {
+            skipped_reason: Some(reason.to_string()),
+            ..Self::default()
+        }
+    }
+}
+
+// Nodos de la colección que la corrida `run_id` no tocó: sus documentos ya no están en Mongo.
+// Los nodos sin `source_collection` son de antes de este campo y también cuentan.
+pub async fn reconcile_nodes(
+    graph: &Graph,
+    collection: &str,
+    labels: &[Label],
+    run_id: &str,
+    options: ReconcileOptions,
+) -> Result<ReconcileReport> {
+    let mut report = ReconcileReport {
+        labels: labels.iter().map(|l| l.to_string()).collect(),
+        dry_run: options.dry_run,
+        ..ReconcileReport::default()
+    };
+
+    for label in labels {
+        let stale_match = format!(
+            "MATCH (n:{label}:{ENTITY_LABEL})
+             WHERE coalesce(n.source_collection, $collection) = $collection
+               AND coalesce(n.ingest_run, '') <> $run_id
+               AND coalesce(n.archived, false) = false",
+            label = quote_identifier(label.as_str())
+        );
+
+        let count_str = format!(
+            "{stale_match}
+             WITH collect(n.id) AS ids
+             RETURN size(ids) AS stale, ids[..$sample] AS sample_ids"
+        );
+        let mut rows = graph
+            .execute(
+                query(&count_str)
+                    .param("collection", collection)
+                    .param("run_id", run_id)
+                    .param("sample", SAMPLE_IDS),
+            )
+            .await?;
+        if let Some(row) = rows.next().await? {
+            let stale: i64 = row.get("stale")?;
+            let sample: Vec<String> = row.get("sample_ids").unwrap_or_default();
+            report.stale += stale;
+            report.sample_ids.extend(sample);
+        }
+
+        if options.dry_run {
+            continue;
+        }
+
+        let apply_str = removal_query(&stale_match, options.mode);
+        let mut rows = graph
+            .execute(
+                query(&apply_str)
+                    .param("collection", collection)
+                    .param("run_id", run_id),
+            )
+            .await?;
+        if let Some(row) = rows.next().await? {
+            report.removed += row.get::<i64>("removed")?;
+        }
+    }
+
+    report.sample_ids.truncate(SAMPLE_IDS as usize);
+    Ok(report)
+}
+
+// Nodos cuyo documento se borró de Mongo (eventos `delete` del change stream)
+pub async fn remove_by_oids(
+    graph: &Graph,
+    collection: &str,
+    oids: &[String],
+    mode: ReconcileMode,
+) -> Result<i64> {
+    if oids.is_empty() {
+        return Ok(0);
+    }
+
+    let matcher = format!(
+        "MATCH (n:{ENTITY_LABEL})
+         WHERE n.original_oid IN $oids
+           AND coalesce(n.source_collection, $collection) = $collection
+           AND coalesce(n.archived, false) = false"
+    );
+    let mut rows = graph
+        .execute(
+            query(&removal_query(&matcher, mode))
+                .param("oids", oids.to_vec())
+                .param("collection", collection),
+        )
+        .await?;
+
+    match rows.next().await? {
+        Some(row) => Ok(row.get::<i64>("removed")?),
+        None => Ok(0),
+    }
+}
+
+// Relaciones de antes de `asserted_by` que ninguna entidad volvió a afirmar.
+// Solo tiene sentido tras una ingesta completa: las vigentes ya se adoptaron.
+pub async fn remove_legacy_edges(graph: &Graph) -> Result<i64> {
+    let mut rows = graph
+        .execute(query(
+            "MATCH ()-[r]->()
+             WHERE r.asserted_by IS NULL AND type(r) <> 'HAS_CHUNK'
+             DELETE r
+             RETURN count(r) AS removed",
+        ))
+        .await?;
+
+    match rows.next().await? {
+        Some(row) => Ok(row.get::<i64>("removed")?),
+        None => Ok(0),
+    }
+}
+
+// Archiva o borra (con sus chunks) los nodos que deja `matcher` en `n`
+fn removal_query(matcher: &str, mode: ReconcileMode) -> String {
+    match mode {
+        ReconcileMode::Archive => format!(
+            "{matcher}
+             SET n.archived = true, n.archived_at = timestamp()
+             RETURN count(n) AS removed"
+        ),
+        ReconcileMode::Delete => format!(
+            "{matcher}
+             OPTIONAL MATCH (n)-[:HAS_CHUNK]->(c)
+             WITH n, collect(c) AS chunks
+             FOREACH (c IN chunks | DETACH DELETE c)
+             DETACH DELETE n
+             RETURN count(*) AS removed"
+        ),
+    }
+}
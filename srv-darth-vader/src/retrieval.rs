@@ -0,0 +1,444 @@
+use crate::{
+    models::{ENTITY_LABEL, LabelRegistry, RelationType},
+    schema::{CHUNK_VECTOR_INDEX, ENTITY_VECTOR_INDEX},
+    state::AppState,
+    utils::bolt_to_json,
+};
+use anyhow::Result;
+use neo4rs::{BoltType, query};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+
+const MAX_TOP_K: usize = 100;
+// Con filtro de labels se piden más candidatos al índice y se filtra después
+const FILTER_OVERSAMPLING: usize = 5;
+
+#[derive(Debug, Deserialize)]
+pub struct SearchRequest {
+    pub query: String,
+    pub top_k: Option<usize>,
+    #[serde(default)]
+    pub labels: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchHit {
+    pub id: String,
+    pub name: Option<String>,
+    pub label: Option<String>,
+    pub score: f64,
+}
+
+pub fn validate_labels(registry: &LabelRegistry, labels: &[String]) -> Result<()> {
+    for label in labels {
+        registry.parse(label)?;
+    }
+    Ok(())
+}
+
+pub async fn vector_search(state: &AppState, request: &SearchRequest) -> Result<Vec<SearchHit>> {
+    let vector = state.embedder.embed(&request.query).await?;
+    search_by_vector(state, request, vector).await
+}
+
+async fn search_by_vector(
+    state: &AppState,
+    request: &SearchRequest,
+    vector: Vec<f32>,
+) -> Result<Vec<SearchHit>> {
+    let top_k = request.top_k.unwrap_or(10).clamp(1, MAX_TOP_K);
+    let candidates = if request.labels.is_empty() {
+        top_k
+    } else {
+        top_k * FILTER_OVERSAMPLING
+    };
+
+    let search_query = query(
+        "CALL db.index.vector.queryNodes($index, $candidates, $vector) YIELD node, score
+         WITH node, score, [l IN labels(node) WHERE l <> $entity_label] AS node_labels
+         WHERE coalesce(node.archived, false) = false
+           AND (size($labels) = 0 OR any(l IN node_labels WHERE l IN $labels))
+         RETURN node.id AS id, node.name AS name, node_labels[0] AS label, score
+         ORDER BY score DESC
+         LIMIT $top_k",
+    )
+    .param("index", ENTITY_VECTOR_INDEX)
+    .param("candidates", candidates as i64)
+    .param("vector", vector)
+    .param("entity_label", ENTITY_LABEL)
+    .param("labels", request.labels.clone())
+    .param("top_k", top_k as i64);
+
+    let mut rows = state.graph.execute(search_query).await?;
+    let mut hits = Vec::new();
+
+    while let Some(row) = rows.next().await? {
+        hits.push(SearchHit {
+            id: row.get("id")?,
+            name: row.get("name").ok(),
+            label: row.get("label").ok(),
+            score: row.get("score")?,
+        });
+    }
+
+    Ok(hits)
+}
+
+// --- GRAPHRAG CONTEXT ---
+
+// Relaciones que se recorren por defecto al expandir el vecindario de cada hit
+pub const CONTEXT_RELATIONS: [&str; 5] = [
+    RelationType::AppearedIn.as_str(),
+    RelationType::BornOn.as_str(),
+    RelationType::BelongsTo.as_str(),
+    RelationType::Pilots.as_str(),
+    RelationType::ResidentOf.as_str(),
+];
+
+const MAX_DEPTH: usize = 3;
+// Tope de caminos por seed, para que un hub no deje sin vecinos al resto
+const MAX_PATHS_PER_SEED: i64 = 200;
+// Relevancia de un vecino = score del hit * HOP_DECAY^saltos
+const HOP_DECAY: f64 = 0.5;
+const CHARS_PER_TOKEN: usize = 4;
+
+// Propiedades que no aportan al prompt
+const HIDDEN_PROPS: [&str; 12] = [
+    "id",
+    "name",
+    "source",
+    "original_oid",
+    "original_swapi_id",
+    "last_updated",
+    "embedding_status",
+    "embedding_error",
+    "embedding",
+    "source_collection",
+    "ingest_run",
+    "placeholder",
+];
+
+#[derive(Debug, Deserialize)]
+pub struct ContextRequest {
+    pub query: String,
+    pub top_k: Option<usize>,
+    #[serde(default)]
+    pub labels: Vec<String>,
+    pub depth: Option<usize>,
+    #[serde(default)]
+    pub relations: Vec<String>,
+    pub max_tokens: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ContextNode {
+    pub id: String,
+    pub name: Option<String>,
+    pub label: Option<String>,
+    pub relevance: f64,
+    pub hops: usize,
+    #[serde(skip)]
+    pub properties: serde_json::Map<String, Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+pub struct ContextEdge {
+    pub source: String,
+    pub relation: String,
+    pub target: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Passage {
+    pub entity_id: String,
+    pub ordinal: i64,
+    pub score: f64,
+    pub text: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ContextResponse {
+    pub seeds: Vec<SearchHit>,
+    pub nodes: Vec<ContextNode>,
+    pub edges: Vec<ContextEdge>,
+    pub passages: Vec<Passage>,
+    pub context: String,
+    pub token_estimate: usize,
+    pub truncated: bool,
+}
+
+pub fn validate_relations(relations: &[String]) -> Result<()> {
+    for relation in relations {
+        RelationType::parse(relation)?;
+    }
+    Ok(())
+}
+
+pub async fn assemble_context(
+    state: &AppState,
+    request: &ContextRequest,
+) -> Result<ContextResponse> {
+    let depth = request.depth.unwrap_or(1).clamp(1, MAX_DEPTH);
+    let max_tokens = request.max_tokens.unwrap_or(2000).max(1);
+    let relations: Vec<String> = if request.relations.is_empty() {
+        CONTEXT_RELATIONS.iter().map(|r| r.to_string()).collect()
+    } else {
+        request.relations.clone()
+    };
+
+    let vector = state.embedder.embed(&request.query).await?;
+    let search = SearchRequest {
+        query: request.query.clone(),
+        top_k: request.top_k,
+        labels: request.labels.clone(),
+    };
+    let seeds = search_by_vector(state, &search, vector.clone()).await?;
+
+    let seed_ids: Vec<String> = seeds.iter().map(|s| s.id.clone()).collect();
+    let (mut nodes, edges) = expand_neighborhood(state, &seeds, &relations, depth).await?;
+    let passages = top_passages(state, &seed_ids, vector, seeds.len().max(1) * 2).await?;
+
+    nodes.sort_by(|a, b| {
+        b.relevance
+            .total_cmp(&a.relevance)
+            .then_with(|| a.hops.cmp(&b.hops))
+    });
+
+    let (context, token_estimate, truncated) =
+        render_context(&nodes, &edges, &passages, max_tokens);
+
+    Ok(ContextResponse {
+        seeds,
+        nodes,
+        edges,
+        passages,
+        context,
+        token_estimate,
+        truncated,
+    })
+}
+
+async fn expand_neighborhood(
+    state: &AppState,
+    seeds: &[SearchHit],
+    relations: &[String],
+    depth: usize,
+) -> Result<(Vec<ContextNode>, Vec<ContextEdge>)> {
+    let mut nodes: HashMap<String, ContextNode> = HashMap::new();
+    let mut edges: BTreeSet<ContextEdge> = BTreeSet::new();
+
+    let seed_scores: HashMap<&str, f64> = seeds.iter().map(|s| (s.id.as_str(), s.score)).collect();
+    if seeds.is_empty() {
+        return Ok((Vec::new(), Vec::new()));
+    }
+
+    // La profundidad no puede ir como parámetro en un patrón de longitud variable
+    let expand_str = format!(
+        "UNWIND $seed_ids AS seed_id
+         MATCH (seed:{ENTITY_LABEL} {{id: seed_id}})
+         CALL {{
+             WITH seed
+             OPTIONAL MATCH path = (seed)-[rels*1..{depth}]-(other)
+             WHERE all(r IN rels WHERE type(r) IN $relations)
+               AND none(n IN nodes(path) WHERE coalesce(n.archived, false))
+             RETURN path
+             LIMIT $max_paths
+         }}
+         RETURN seed.id AS seed_id,
+                CASE WHEN path IS NULL THEN 0 ELSE length(path) END AS hops,
+                [n IN CASE WHEN path IS NULL THEN [seed] ELSE nodes(path) END |
+                    {{id: n.id, name: n.name,
+                      label: head([l IN labels(n) WHERE l <> $entity_label]),
+                      props: n {{.*, embedding: null}}}}] AS path_nodes,
+                [r IN CASE WHEN path IS NULL THEN [] ELSE relationships(path) END |
+                    {{source: startNode(r).id, relation: type(r), target: endNode(r).id}}] AS path_rels"
+    );
+
+    let seed_ids: Vec<String> = seeds.iter().map(|s| s.id.clone()).collect();
+    let mut rows = state
+        .graph
+        .execute(
+            query(&expand_str)
+                .param("seed_ids", seed_ids)
+                .param("relations", relations.to_vec())
+                .param("entity_label", ENTITY_LABEL)
+                .param("max_paths", MAX_PATHS_PER_SEED),
+        )
+        .await?;
+
+    while let Some(row) = rows.next().await? {
+        let seed_id: String = row.get("seed_id")?;
+        // Las propiedades pueden traer tipos de Neo4j (date, datetime) que no pasan a JSON directo
+        let path_nodes: Vec<BoltType> = row.get("path_nodes")?;
+        let path_rels: Vec<Value> = row.get("path_rels")?;
+        let seed_score = seed_scores.get(seed_id.as_str()).copied().unwrap_or(0.0);
+
+        // La posición en el camino es la distancia al seed
+        for (hops, node) in path_nodes.iter().map(bolt_to_json).enumerate() {
+            let Some(id) = node["id"].as_str().map(str::to_string) else {
+                continue;
+            };
+            let relevance = seed_score * HOP_DECAY.powi(hops as i32);
+
+            let entry = nodes.entry(id.clone()).or_insert_with(|| ContextNode {
+                id,
+                name: node["name"].as_str().map(str::to_string),
+                label: node["label"].as_str().map(str::to_string),
+                relevance,
+                hops,
+                properties: visible_properties(&node["props"]),
+            });
+            if relevance > entry.relevance {
+                entry.relevance = relevance;
+            }
+            entry.hops = entry.hops.min(hops);
+        }
+
+        for rel in path_rels {
+            if let (Some(source), Some(relation), Some(target)) = (
+                rel["source"].as_str(),
+                rel["relation"].as_str(),
+                rel["target"].as_str(),
+            ) {
+                edges.insert(ContextEdge {
+                    source: source.to_string(),
+                    relation: relation.to_string(),
+                    target: target.to_string(),
+                });
+            }
+        }
+    }
+
+    Ok((nodes.into_values().collect(), edges.into_iter().collect()))
+}
+
+fn visible_properties(props: &Value) -> serde_json::Map<String, Value> {
+    let mut map = serde_json::Map::new();
+    if let Some(props) = props.as_object() {
+        for (key, value) in props {
+            if HIDDEN_PROPS.contains(&key.as_str())
+                || key.ends_with("_ids")
+                || key.ends_with("_raw")
+            {
+                continue;
+            }
+            if matches!(value, Value::String(_) | Value::Number(_) | Value::Bool(_)) {
+                map.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    map
+}
+
+// Pasajes (chunks) más cercanos al query, restringidos a las entidades semilla
+async fn top_passages(
+    state: &AppState,
+    seed_ids: &[String],
+    vector: Vec<f32>,
+    limit: usize,
+) -> Result<Vec<Passage>> {
+    if seed_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let passages_query = query(
+        "CALL db.index.vector.queryNodes($index, $candidates, $vector) YIELD node, score
+         WHERE node.entity_id IN $seed_ids
+         RETURN node.entity_id AS entity_id, node.ordinal AS ordinal, node.text AS text, score
+         ORDER BY score DESC
+         LIMIT $limit",
+    )
+    .param("index", CHUNK_VECTOR_INDEX)
+    .param("candidates", (limit * FILTER_OVERSAMPLING * 4) as i64)
+    .param("vector", vector)
+    .param("seed_ids", seed_ids.to_vec())
+    .param("limit", limit as i64);
+
+    let mut rows = state.graph.execute(passages_query).await?;
+    let mut passages = Vec::new();
+
+    while let Some(row) = rows.next().await? {
+        passages.push(Passage {
+            entity_id: row.get("entity_id")?,
+            ordinal: row.get("ordinal").unwrap_or_default(),
+            score: row.get("score")?,
+            text: row.get("text").unwrap_or_default(),
+        });
+    }
+
+    Ok(passages)
+}
+
+// Arma el texto para el prompt por orden de relevancia hasta agotar el presupuesto de tokens
+fn render_context(
+    nodes: &[ContextNode],
+    edges: &[ContextEdge],
+    passages: &[Passage],
+    max_tokens: usize,
+) -> (String, usize, bool) {
+    let names: HashMap<&str, &str> = nodes
+        .iter()
+        .map(|n| (n.id.as_str(), n.name.as_deref().unwrap_or(n.id.as_str())))
+        .collect();
+    let display = |id: &str| names.get(id).copied().unwrap_or(id).to_string();
+
+    let mut sections: Vec<String> = Vec::new();
+
+    for passage in passages {
+        sections.push(format!(
+            "[Pasaje de {}] {}",
+            display(&passage.entity_id),
+            passage.text
+        ));
+    }
+
+    for node in nodes {
+        let facts: Vec<String> = node
+            .properties
+            .iter()
+            .map(|(k, v)| match v {
+                Value::String(s) => format!("{}: {}", k, s),
+                other => format!("{}: {}", k, other),
+            })
+            .collect();
+
+        let relations: Vec<String> = edges
+            .iter()
+            .filter(|e| e.source == node.id)
+            .map(|e| format!("{} -> {}", e.relation, display(&e.target)))
+            .collect();
+
+        let mut section = format!(
+            "[{}] {}",
+            node.label.as_deref().unwrap_or("Entity"),
+            display(&node.id)
+        );
+        if !facts.is_empty() {
+            section.push_str(&format!(" ({})", facts.join("; ")));
+        }
+        if !relations.is_empty() {
+            section.push_str(&format!("\n  {}", relations.join("\n  ")));
+        }
+        sections.push(section);
+    }
+
+    let budget_chars = max_tokens * CHARS_PER_TOKEN;
+    let mut context = String::new();
+    let mut truncated = false;
+
+    for section in sections {
+        let separator = if context.is_empty() { 0 } else { 2 };
+        if context.len() + separator + section.len() > budget_chars {
+            truncated = true;
+            continue;
+        }
+        if separator > 0 {
+            context.push_str("\n\n");
+        }
+        context.push_str(&section);
+    }
+
+    let token_estimate = context.len().div_ceil(CHARS_PER_TOKEN);
+    (context, token_estimate, truncated)
+}
@@ -0,0 +1,170 @@
+use crate::{
+    cypher::quote_identifier,
+    models::{CHUNK_LABEL, ENTITY_LABEL, LabelRegistry},
+};
+use anyhow::Result;
+use neo4rs::{Graph, query};
+use serde::Serialize;
+use serde_json::Value;
+
+pub const ENTITY_VECTOR_INDEX: &str = "entity_embedding";
+pub const CHUNK_VECTOR_INDEX: &str = "chunk_embedding";
+
+#[derive(Debug, Serialize)]
+pub struct VectorIndexStatus {
+    pub name: String,
+    pub label: String,
+    pub dimensions: Option<i64>,
+    pub similarity: Option<String>,
+    pub created: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SchemaFailure {
+    pub name: String,
+    pub label: String,
+    pub error: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct SchemaReport {
+    pub constraints: Vec<String>,
+    pub vector_indexes: Vec<VectorIndexStatus>,
+    pub drift: Vec<String>,
+    pub failures: Vec<SchemaFailure>,
+}
+
+impl SchemaReport {
+    fn fail(&mut self, name: &str, label: &str, error: anyhow::Error) {
+        eprintln!("⚠️ No se pudo crear {} ({}): {}", name, label, error);
+        self.failures.push(SchemaFailure {
+            name: name.to_string(),
+            label: label.to_string(),
+            error: error.to_string(),
+        });
+    }
+}
+
+// Crea (si faltan) las constraints de unicidad por label y los índices vectoriales,
+// y reporta drift si un índice existente no coincide con el modelo configurado.
+// Cada sentencia va por separado: una que falla queda en `failures` y no frena el resto.
+pub async fn bootstrap_schema(
+    graph: &Graph,
+    labels: &LabelRegistry,
+    dimension: usize,
+    similarity: &str,
+) -> SchemaReport {
+    let mut report = SchemaReport::default();
+
+    let labels = labels.known().into_iter().chain([CHUNK_LABEL.to_string()]);
+    for label in labels {
+        let name = format!("{}_id_unique", label.to_lowercase());
+        let constraint_str = format!(
+            "CREATE CONSTRAINT {} IF NOT EXISTS
+             FOR (n:{}) REQUIRE n.id IS UNIQUE",
+            quote_identifier(&name),
+            quote_identifier(&label)
+        );
+        match graph.run(query(&constraint_str)).await {
+            Ok(()) => report.constraints.push(name),
+            Err(e) => report.fail(&name, &label, e.into()),
+        }
+    }
+
+    for (name, label) in [
+        (ENTITY_VECTOR_INDEX, ENTITY_LABEL),
+        (CHUNK_VECTOR_INDEX, CHUNK_LABEL),
+    ] {
+        let status = match ensure_vector_index(graph, name, label, dimension, similarity).await {
+            Ok(status) => status,
+            Err(e) => {
+                report.fail(name, label, e);
+                continue;
+            }
+        };
+
+        if let Some(existing) = status.dimensions
+            && existing != dimension as i64
+        {
+            report.drift.push(format!(
+                "Índice {} tiene {} dimensiones, el modelo configurado usa {}",
+                name, existing, dimension
+            ));
+        }
+        if let Some(existing) = &status.similarity
+            && !existing.eq_ignore_ascii_case(similarity)
+        {
+            report.drift.push(format!(
+                "Índice {} usa similitud {}, la configurada es {}",
+                name, existing, similarity
+            ));
+        }
+
+        report.vector_indexes.push(status);
+    }
+
+    report
+}
+
+async fn ensure_vector_index(
+    graph: &Graph,
+    name: &str,
+    label: &str,
+    dimension: usize,
+    similarity: &str,
+) -> Result<VectorIndexStatus> {
+    if let Some(status) = describe_vector_index(graph, name, label).await? {
+        return Ok(status);
+    }
+
+    // OPTIONS no acepta parámetros; la similitud ya viene validada desde la config
+    let index_str = format!(
+        "CREATE VECTOR INDEX {} IF NOT EXISTS
+         FOR (n:{}) ON n.embedding
+         OPTIONS {{indexConfig: {{
+             `vector.dimensions`: {dimension},
+             `vector.similarity_function`: '{similarity}'
+         }}}}",
+        quote_identifier(name),
+        quote_identifier(label)
+    );
+    graph.run(query(&index_str)).await?;
+
+    Ok(VectorIndexStatus {
+        name: name.to_string(),
+        label: label.to_string(),
+        dimensions: Some(dimension as i64),
+        similarity: Some(similarity.to_lowercase()),
+        created: true,
+    })
+}
+
+async fn describe_vector_index(
+    graph: &Graph,
+    name: &str,
+    label: &str,
+) -> Result<Option<VectorIndexStatus>> {
+    let mut rows = graph
+        .execute(
+            query("SHOW INDEXES YIELD name, type, options WHERE name = $name RETURN options")
+                .param("name", name),
+        )
+        .await?;
+
+    let Some(row) = rows.next().await? else {
+        return Ok(None);
+    };
+
+    let options: Value = row.get("options").unwrap_or(Value::Null);
+    let config = &options["indexConfig"];
+
+    Ok(Some(VectorIndexStatus {
+        name: name.to_string(),
+        label: label.to_string(),
+        dimensions: config["vector.dimensions"].as_i64(),
+        similarity: config["vector.similarity_function"]
+            .as_str()
+            .map(str::to_lowercase),
+        created: false,
+    }))
+}
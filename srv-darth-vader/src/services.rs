@@ -1,184 +1,584 @@
-use crate::{models::GraphableSource, state::AppState, utils::bolt_map_from_serde};
+use crate::{
+    embedding_cache::EmbeddingCache,
+    graph_writer::{ChunkEmbedding, GraphBatchWriter, NodeEmbedding, WriteOutcome},
+    jobs::{GroupMark, IngestJob, JobControl, PassProgress, StopRequest},
+    mapping::MappedDocument,
+    models::{
+        CharacterRaw, GraphableSource, Label, MoviesRaw, PlanetRaw, SpeciesRaw, StarshipRaw,
+        VehicleRaw,
+    },
+    reconcile::{ReconcileOptions, ReconcileReport, reconcile_nodes},
+    state::AppState,
+    watcher::watched_collections,
+};
 use anyhow::Result;
 use futures::stream::TryStreamExt;
-use mongodb::bson::doc;
-use neo4rs::{BoltMap, BoltNull, BoltString, BoltType, Graph, query};
+use mongodb::bson::{Bson, Document, doc, from_document};
 use serde::de::DeserializeOwned;
-use serde_json::Value;
-use std::sync::Arc;
-
-// --- GEMINI CLIENT LOGIC ---
-pub async fn get_gemini_embedding(
-    client: &reqwest::Client,
-    key: &str,
-    text: &str,
-) -> Result<Vec<f32>> {
-    if key.trim().is_empty() {
-        return Err(anyhow::anyhow!(
-            "CRÍTICO: La Google API Key está vacía. Revisa tu .env"
-        ));
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+};
+use tokio::task::JoinHandle;
+
+// Cada cuántos documentos se persiste el progreso del job
+const PROGRESS_FLUSH_EVERY: u64 = 25;
+
+pub async fn run_ingest_job(state: Arc<AppState>, mut job: IngestJob) {
+    job.start();
+    if let Err(e) = state.jobs.save(&job).await {
+        eprintln!("⚠️ No se pudo guardar el job {}: {}", job.id, e);
     }
 
-    let url =
-        "https://generativelanguage.googleapis.com/v1beta/models/text-embedding-004:embedContent";
+    let control = state.job_registry.register(&job);
+    let heartbeat = spawn_lease_heartbeat(state.clone(), &job);
 
-    if text.trim().is_empty() {
-        return Err(anyhow::anyhow!(
-            "Texto vacío, no se puede generar embedding"
-        ));
+    let collection_name = job.collection.clone();
+    let result = match decoder_for(&state, &collection_name) {
+        Some(decode) => {
+            let decode = |doc| decode(doc);
+            process_collection(&collection_name, state.clone(), &mut job, &control, decode).await
+        }
+        None => Err(anyhow::anyhow!("Colección no mapeada")),
+    };
+
+    match &result {
+        Err(e) => eprintln!("Job failed for {}: {:?}", collection_name, e),
+        Ok(Some(stop)) => println!(
+            "⏹️ Job {} detenido ({:?}) tras {} documentos",
+            job.id,
+            stop,
+            job.seen()
+        ),
+        Ok(None) => {}
     }
 
-    let res = client
-        .post(url)
-        .header("x-goog-api-key", key) // <--- ESTO ES LA CLAVE
-        .header("Content-Type", "application/json")
-        .json(&serde_json::json!({
-            "content": {
-                "parts": [{ "text": text }]
+    job.finish(&result);
+    // El heartbeat solo termina solo si perdió el lease (y ya pidió la pausa)
+    if heartbeat.is_finished() {
+        job.last_error = Some(lease_lost_error(&collection_name));
+    }
+    if let Err(e) = state.jobs.save(&job).await {
+        eprintln!("⚠️ No se pudo guardar el job {}: {}", job.id, e);
+    }
+    heartbeat.abort();
+    state.job_registry.unregister(&job.id);
+    if let Err(e) = state.job_registry.release(&collection_name, &job.id).await {
+        eprintln!(
+            "⚠️ No se pudo liberar el lease de {}: {}",
+            collection_name, e
+        );
+    }
+}
+
+// Renueva el lease de la colección mientras el job corre. Si lo pierde pausa el
+// job: seguir escribiendo pisaría a la réplica que ahora tiene el lease.
+pub fn spawn_lease_heartbeat(state: Arc<AppState>, job: &IngestJob) -> JoinHandle<()> {
+    let collection = job.collection.clone();
+    let job_id = job.id.clone();
+    tokio::spawn(async move {
+        let every = state.job_registry.renew_every();
+        loop {
+            tokio::time::sleep(every).await;
+            match state.job_registry.renew(&collection, &job_id).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    eprintln!(
+                        "⚠️ El job {} perdió el lease de {}: se pausa",
+                        job_id, collection
+                    );
+                    state.job_registry.request_stop(&job_id, StopRequest::Pause);
+                    break;
+                }
+                Err(e) => eprintln!("⚠️ No se pudo renovar el lease de {}: {}", collection, e),
             }
-        }))
-        .send()
-        .await?;
+        }
+    })
+}
 
-    if !res.status().is_success() {
-        let error_text = res.text().await?;
-        return Err(anyhow::anyhow!("Gemini API Error: {}", error_text));
-    }
+pub fn lease_lost_error(collection: &str) -> String {
+    format!(
+        "Se perdió el lease de {}: otra réplica puede estar ingestándola",
+        collection
+    )
+}
 
-    let json: Value = res.json().await?;
+// Documento ya decodificado, venga de un struct tipado o del archivo de mapping
+pub type SourceDoc = Box<dyn GraphableSource + Send + Sync>;
+pub type Decoder = Arc<dyn Fn(Document) -> Result<SourceDoc> + Send + Sync>;
 
-    let values = json["embedding"]["values"]
-        .as_array()
-        .ok_or_else(|| anyhow::anyhow!("Formato respuesta Gemini inválido: {:?}", json))?
-        .iter()
-        .map(|v| v.as_f64().unwrap_or(0.0) as f32)
-        .collect();
+// Decodificador de la colección: primero los tipos en Rust, luego el mapping
+pub fn decoder_for(state: &AppState, collection: &str) -> Option<Decoder> {
+    let decoder = match collection {
+        "characters_raw" => typed::<CharacterRaw>(),
+        "movies_raw" => typed::<MoviesRaw>(),
+        "planets_raw" => typed::<PlanetRaw>(),
+        "species_raw" => typed::<SpeciesRaw>(),
+        "starships_raw" => typed::<StarshipRaw>(),
+        "vehicles_raw" => typed::<VehicleRaw>(),
+        other => {
+            let mapping = state.mappings.get(other)?;
+            Arc::new(move |doc| {
+                MappedDocument::new(mapping.clone(), doc).map(|d| Box::new(d) as SourceDoc)
+            })
+        }
+    };
+    Some(decoder)
+}
 
-    Ok(values)
+fn typed<T>() -> Decoder
+where
+    T: DeserializeOwned + GraphableSource + Send + Sync + 'static,
+{
+    Arc::new(|doc| Ok(Box::new(from_document::<T>(doc)?) as SourceDoc))
 }
 
-pub async fn process_collection<T>(collection_name: &str, state: Arc<AppState>) -> Result<()>
+pub async fn process_collection<T, F>(
+    collection_name: &str,
+    state: Arc<AppState>,
+    job: &mut IngestJob,
+    control: &JobControl,
+    decode: F,
+) -> Result<Option<StopRequest>>
 where
-    T: DeserializeOwned + GraphableSource + Send + Sync + Unpin,
+    T: GraphableSource + Send + Sync + 'static,
+    F: Fn(Document) -> Result<T>,
 {
-    println!(">>> Procesando colección: {}", collection_name);
+    println!(
+        ">>> Procesando colección: {} (job {})",
+        collection_name, job.id
+    );
 
     let collection = state
         .mongo
         .database(&state.mongo_db_name)
-        .collection::<T>(collection_name);
-
-    let mut cursor = collection.find(doc! {}).await?;
-    let http_client = reqwest::Client::new();
-
-    while let Some(doc) = cursor.try_next().await? {
-        let raw_text = doc.get_rich_text();
-        let entity_name = doc.get_entity_name();
-
-        let embedding_vector = if raw_text.len() > 5 {
-            let context_text = format!("About {}: {}", entity_name, raw_text);
-            match get_gemini_embedding(&http_client, &state.gemini_key, &context_text).await {
-                Ok(vec) => vec,
-                Err(e) => {
-                    eprintln!("⚠️ Error embedding para {}: {}", entity_name, e);
-                    vec![]
-                }
+        .collection::<Document>(collection_name);
+
+    // Orden por `_id` para que el checkpoint marque un prefijo ya escrito
+    let filter = match &job.checkpoint {
+        Some(checkpoint) => {
+            println!(
+                "⏩ Retomando {} desde _id {}",
+                collection_name, checkpoint.last_id
+            );
+            doc! { "_id": { "$gt": checkpoint.last_id.clone() } }
+        }
+        None => doc! {},
+    };
+    let mut cursor = collection.find(filter).sort(doc! { "_id": 1 }).await?;
+    let mut writer = GraphBatchWriter::new(
+        state.graph.clone(),
+        state.ingest.graph_batch_size,
+        collection_name,
+        &job.id,
+    )
+    .reconcile_edges(state.ingest.reconcile_edges);
+
+    // Lotes de embeddings en vuelo, en orden de llegada
+    let embed_batch_size = state
+        .ingest
+        .embed_batch_size
+        .min(state.embedder.max_batch_size())
+        .max(1);
+    // Cada grupo en vuelo lleva el `_id` de su último documento y los inválidos previos
+    let mut in_flight: VecDeque<(JoinHandle<EmbeddedGroup<T>>, GroupMark)> = VecDeque::new();
+    let mut group: Vec<T> = Vec::with_capacity(embed_batch_size);
+    let mut group_last_id: Option<Bson> = None;
+    let mut pass = PassProgress::default();
+    let mut last_saved = 0;
+    let mut last_checkpoint = job.seen();
+    let mut stopped = None;
+
+    while let Some(raw_doc) = cursor.try_next().await? {
+        if let Some(stop) = control.stop_requested() {
+            stopped = Some(stop);
+            break;
+        }
+
+        let raw_id = raw_doc.get("_id").cloned();
+        let doc: T = match decode(raw_doc) {
+            Ok(doc) => doc,
+            Err(e) => {
+                eprintln!("⚠️ Documento inválido en {}: {}", collection_name, e);
+                pass.skip();
+                continue;
             }
-        } else {
-            vec![]
         };
 
-        if let Err(e) = ingest_entity_to_graph(&state.graph, &doc, embedding_vector).await {
-            eprintln!("❌ Error insertando en Neo4j ({}): {}", entity_name, e);
-        } else {
-            println!("✅ Ingestado: {}", entity_name);
+        group.push(doc);
+        group_last_id = raw_id;
+        if group.len() >= embed_batch_size {
+            let handle = spawn_embedding(state.clone(), std::mem::take(&mut group));
+            in_flight.push_back((handle, pass.close_group(group_last_id.take())));
+        }
+
+        if in_flight.len() >= state.ingest.embed_concurrency
+            && let Some((handle, mark)) = in_flight.pop_front()
+        {
+            write_group(job, &mut writer, handle.await?).await;
+            pass.written(job, mark);
+            checkpoint(
+                &state,
+                job,
+                &mut writer,
+                &pass.last_written_id,
+                &mut last_checkpoint,
+            )
+            .await;
+            save_progress(&state, job, &mut last_saved).await;
+        }
+    }
+
+    match stopped {
+        // Lo ya embebido se escribe; el grupo a medio armar se relee al retomar
+        Some(StopRequest::Pause) => {
+            while let Some((handle, mark)) = in_flight.pop_front() {
+                write_group(job, &mut writer, handle.await?).await;
+                pass.written(job, mark);
+            }
+            record_outcomes(job, writer.flush().await);
+            if let Some(last_id) = pass.last_written_id {
+                job.set_checkpoint(last_id);
+            }
+            return Ok(stopped);
         }
+        Some(StopRequest::Cancel) => {
+            for (handle, _) in in_flight {
+                handle.abort();
+            }
+            record_outcomes(job, writer.flush().await);
+            return Ok(stopped);
+        }
+        None => {}
+    }
+
+    if !group.is_empty() {
+        let mark = pass.close_group(group_last_id);
+        in_flight.push_back((spawn_embedding(state.clone(), group), mark));
+    }
+    while let Some((handle, mark)) = in_flight.pop_front() {
+        write_group(job, &mut writer, handle.await?).await;
+        pass.written(job, mark);
+        checkpoint(
+            &state,
+            job,
+            &mut writer,
+            &pass.last_written_id,
+            &mut last_checkpoint,
+        )
+        .await;
+        save_progress(&state, job, &mut last_saved).await;
+    }
+    pass.finish(job);
+
+    record_outcomes(job, writer.flush().await);
+
+    if let Some(options) = job.reconcile {
+        let report = reconcile_collection(&state, job, &writer.labels_seen(), options).await?;
+        println!(
+            "🧹 Reconciliación {}: {} nodos obsoletos, {} removidos{}",
+            collection_name,
+            report.stale,
+            report.removed,
+            if report.dry_run { " (dry-run)" } else { "" }
+        );
+        job.reconciled = Some(report);
     }
 
     println!("<<< Finalizado: {}", collection_name);
-    Ok(())
+    Ok(None)
+}
+
+// Solo tras una pasada completa y limpia: un documento que falló o se saltó no
+// tocó su nodo y se vería como borrado de Mongo.
+async fn reconcile_collection(
+    state: &AppState,
+    job: &IngestJob,
+    labels: &[Label],
+    options: ReconcileOptions,
+) -> Result<ReconcileReport> {
+    if job.failed > 0 || job.skipped > 0 {
+        return Ok(ReconcileReport::skipped(format!(
+            "Pasada incompleta ({} fallidos, {} inválidos)",
+            job.failed, job.skipped
+        )));
+    }
+    if labels.is_empty() {
+        return Ok(ReconcileReport::skipped(
+            "Colección vacía: no se infiere qué borrar",
+        ));
+    }
+    // Se vuelve a mirar al final: el watcher pudo activarse durante la pasada
+    if !watched_collections(state, std::slice::from_ref(&job.collection))
+        .await?
+        .is_empty()
+    {
+        return Ok(ReconcileReport::skipped(
+            "Watcher activo en la colección: sus nodos no llevan el ingest_run de este job",
+        ));
+    }
+
+    reconcile_nodes(&state.graph, &job.collection, labels, &job.id, options).await
+}
+
+pub struct EmbeddedGroup<T> {
+    docs: Vec<T>,
+    embeddings: Vec<NodeEmbedding>,
+    chunks: Vec<Vec<ChunkEmbedding>>,
+    api_calls: u64,
+    cache_hits: u64,
+}
+
+fn embedding_text(doc: &dyn GraphableSource) -> Option<String> {
+    let raw_text = doc.get_rich_text();
+    if raw_text.len() > 5 {
+        Some(format!("About {}: {}", doc.get_entity_name(), raw_text))
+    } else {
+        None
+    }
 }
 
-fn json_map_to_bolt_type(map: serde_json::Map<String, Value>) -> BoltType {
-    let mut bolt_map = BoltMap::new();
+// Embebe un grupo de documentos (y sus chunks) en una task aparte
+pub fn spawn_embedding<T>(state: Arc<AppState>, docs: Vec<T>) -> JoinHandle<EmbeddedGroup<T>>
+where
+    T: GraphableSource + Send + 'static,
+{
+    tokio::spawn(async move {
+        // Todos los textos del grupo van a una sola lista; cada entidad / chunk guarda su posición
+        let mut texts = Vec::new();
+        let mut entity_slots = Vec::with_capacity(docs.len());
+        let mut chunk_slots = Vec::with_capacity(docs.len());
 
-    for (key, value) in map {
-        let bolt_key: BoltString = key.into();
-        let bolt_val: BoltType = match value {
-            Value::String(s) => s.into(),
-            Value::Number(n) => {
-                if let Some(i) = n.as_i64() {
-                    i.into()
-                } else if let Some(f) = n.as_f64() {
-                    f.into()
+        for doc in &docs {
+            let entity_slot = embedding_text(doc).map(|text| {
+                texts.push(text);
+                texts.len() - 1
+            });
+            entity_slots.push(entity_slot);
+
+            let mut slots = Vec::new();
+            if let Some(chunker) = &state.chunker
+                && let Some(entity_slot) = entity_slot
+            {
+                let mut chunks = chunker.chunk(&doc.get_rich_text());
+                if chunks.len() == 1 {
+                    // Un solo chunk es el texto entero: usa el vector de la entidad
+                    slots.extend(chunks.pop().map(|chunk| (chunk, entity_slot)));
                 } else {
-                    n.to_string().into()
+                    let name = doc.get_entity_name();
+                    for chunk in chunks {
+                        texts.push(format!("About {}: {}", name, chunk));
+                        slots.push((chunk, texts.len() - 1));
+                    }
                 }
             }
-            Value::Bool(b) => b.into(),
-            Value::Null => BoltType::Null(BoltNull),
+            chunk_slots.push(slots);
+        }
 
-            _ => value.to_string().into(),
+        let EmbeddedTexts {
+            results,
+            api_calls,
+            cache_hits,
+        } = embed_texts(&state, texts).await;
+
+        let to_node_embedding = |slot: usize| match &results[slot] {
+            Ok(vector) => NodeEmbedding::Vector(vector.clone()),
+            Err(e) => NodeEmbedding::Failed(e.clone()),
         };
-        bolt_map.put(bolt_key, bolt_val);
+
+        let embeddings = entity_slots
+            .into_iter()
+            .map(|slot| slot.map_or(NodeEmbedding::NoText, to_node_embedding))
+            .collect();
+
+        let chunks = chunk_slots
+            .into_iter()
+            .map(|slots| {
+                slots
+                    .into_iter()
+                    .enumerate()
+                    .map(|(ordinal, (text, slot))| ChunkEmbedding {
+                        ordinal: ordinal as i64,
+                        text,
+                        embedding: to_node_embedding(slot),
+                    })
+                    .collect()
+            })
+            .collect();
+
+        EmbeddedGroup {
+            docs,
+            embeddings,
+            chunks,
+            api_calls,
+            cache_hits,
+        }
+    })
+}
+
+struct EmbeddedTexts {
+    results: Vec<Result<Vec<f32>, String>>,
+    api_calls: u64,
+    cache_hits: u64,
+}
+
+// Resuelve primero contra la cache (mismo texto y modelo) y manda el resto al
+// proveedor en lotes de `max_batch_size`, sin repetir textos idénticos.
+async fn embed_texts(state: &AppState, texts: Vec<String>) -> EmbeddedTexts {
+    let model = state.embedder.model_name().to_string();
+    let dimension = state.embedder.dimension();
+    let keys: Vec<String> = texts
+        .iter()
+        .map(|text| EmbeddingCache::key(&model, dimension, text))
+        .collect();
+
+    let mut resolved = match &state.embedding_cache {
+        Some(cache) => cache.get_many(&keys, dimension).await.unwrap_or_else(|e| {
+            eprintln!("⚠️ Cache de embeddings no disponible: {}", e);
+            HashMap::new()
+        }),
+        None => HashMap::new(),
+    };
+    let cache_hits = keys.iter().filter(|k| resolved.contains_key(*k)).count() as u64;
+
+    let mut misses: Vec<(String, String)> = Vec::new();
+    for (key, text) in keys.iter().zip(&texts) {
+        if !resolved.contains_key(key) && !misses.iter().any(|(k, _)| k == key) {
+            misses.push((key.clone(), text.clone()));
+        }
+    }
+
+    let mut failures: HashMap<String, String> = HashMap::new();
+    let mut api_calls = 0;
+
+    for batch in misses.chunks(state.embedder.max_batch_size().max(1)) {
+        let batch_texts: Vec<String> = batch.iter().map(|(_, text)| text.clone()).collect();
+        api_calls += 1;
+
+        match state.embedder.embed_batch(&batch_texts).await {
+            Ok(vectors) => {
+                let fresh: Vec<(String, Vec<f32>)> = batch
+                    .iter()
+                    .map(|(key, _)| key.clone())
+                    .zip(vectors)
+                    .collect();
+
+                if let Some(cache) = &state.embedding_cache
+                    && let Err(e) = cache.put_many(&model, fresh.clone()).await
+                {
+                    eprintln!("⚠️ No se pudo guardar en la cache de embeddings: {}", e);
+                }
+                resolved.extend(fresh);
+            }
+            Err(e) => {
+                eprintln!(
+                    "⚠️ Error embedding para lote de {} textos: {:#}",
+                    batch.len(),
+                    e
+                );
+                for (key, _) in batch {
+                    failures.insert(key.clone(), format!("{:#}", e));
+                }
+            }
+        }
+    }
+
+    let results = keys
+        .iter()
+        .map(|key| match resolved.get(key) {
+            Some(vector) => Ok(vector.clone()),
+            None => Err(failures
+                .get(key)
+                .cloned()
+                .unwrap_or_else(|| "Embedding no disponible".to_string())),
+        })
+        .collect();
+
+    EmbeddedTexts {
+        results,
+        api_calls,
+        cache_hits,
     }
-    BoltType::Map(bolt_map)
 }
 
-pub async fn ingest_entity_to_graph<T>(
-    graph: &Arc<Graph>,
-    entity: &T,
-    embedding_vector: Vec<f32>,
-) -> Result<(), Box<dyn std::error::Error>>
-where
-    T: GraphableSource + Sync + Send,
-{
-    let label = entity.get_entity_label();
-    let id = entity.get_entity_id();
-    let name = entity.get_entity_name();
-
-    let props_map = entity.get_metadata_as_map();
-    let props_bolt = json_map_to_bolt_type(props_map);
-
-    let node_query_str = format!(
-        "MERGE (n:{label} {{id: $id}}) 
-         SET n += $props, 
-             n.embedding = $vector,
-             n.name = $name,
-             n.last_updated = timestamp()"
-    );
+pub async fn write_group<T: GraphableSource>(
+    job: &mut IngestJob,
+    writer: &mut GraphBatchWriter,
+    group: EmbeddedGroup<T>,
+) {
+    job.embedding_calls += group.api_calls;
+    job.cache_hits += group.cache_hits;
 
-    let node_query = query(&node_query_str)
-        .param("id", id)
-        .param("vector", embedding_vector)
-        .param("name", name)
-        .param("props", props_bolt);
+    let entries = group.docs.iter().zip(group.embeddings).zip(group.chunks);
+    for ((doc, embedding), chunks) in entries {
+        match &embedding {
+            NodeEmbedding::Vector(_) => job.embedded += 1,
+            NodeEmbedding::Failed(_) => job.embedding_failed += 1,
+            NodeEmbedding::NoText => {}
+        }
+        job.chunks += chunks.len() as u64;
+        writer.push(doc, embedding, chunks);
+
+        if writer.is_full() {
+            record_outcomes(job, writer.flush().await);
+        }
+    }
+}
 
-    graph.run(node_query).await?;
+// Cada `checkpoint_every` documentos se vacía el writer: todo hasta `last_id`
+// queda escrito (o registrado como fallido) y un resume puede saltarlo.
+async fn checkpoint(
+    state: &AppState,
+    job: &mut IngestJob,
+    writer: &mut GraphBatchWriter,
+    last_id: &Option<Bson>,
+    last_checkpoint: &mut u64,
+) {
+    let every = state.ingest.checkpoint_every;
+    let Some(last_id) = last_id else {
+        return;
+    };
+    if every == 0 || job.seen() - *last_checkpoint < every {
+        return;
+    }
 
-    let edges = entity.get_edges();
+    record_outcomes(job, writer.flush().await);
+    job.set_checkpoint(last_id.clone());
+    *last_checkpoint = job.seen();
 
-    for edge in edges {
-        let edge_query_str = format!(
-            "MERGE (source:{source_label} {{id: $source_id}})
-             MERGE (target:{target_label} {{id: $target_id}})
-             MERGE (source)-[r:{relation}]->(target)",
-            source_label = edge.source_label,
-            target_label = edge.target_label,
-            relation = edge.relation_type
+    if let Err(e) = state.jobs.save(job).await {
+        eprintln!(
+            "⚠️ No se pudo guardar el checkpoint del job {}: {}",
+            job.id, e
         );
+    }
+}
 
-        let edge_query = query(&edge_query_str)
-            .param("source_id", edge.source_id)
-            .param("target_id", edge.target_id);
+async fn save_progress(state: &AppState, job: &IngestJob, last_saved: &mut u64) {
+    if job.seen() - *last_saved < PROGRESS_FLUSH_EVERY {
+        return;
+    }
+    *last_saved = job.seen();
 
-        if let Err(e) = graph.run(edge_query).await {
-            eprintln!(
-                "⚠️ Warning edge ({} -> {}): {:?}",
-                label, edge.target_label, e
-            );
-        }
+    if let Err(e) = state.jobs.save(job).await {
+        eprintln!(
+            "⚠️ No se pudo guardar el progreso del job {}: {}",
+            job.id, e
+        );
     }
+}
 
-    Ok(())
+pub fn record_outcomes(job: &mut IngestJob, outcomes: Vec<WriteOutcome>) {
+    for outcome in outcomes {
+        match outcome.error {
+            Some(e) => {
+                eprintln!("❌ Error insertando en Neo4j ({}): {}", outcome.name, e);
+                job.record_failure(format!("{}: {}", outcome.name, e));
+            }
+            None => {
+                println!("✅ Ingestado: {}", outcome.name);
+                job.processed += 1;
+            }
+        }
+    }
 }
@@ -1,3 +1,12 @@
+use crate::{
+    chunking::Chunker,
+    config::IngestConfig,
+    embedding_cache::EmbeddingCache,
+    embeddings::EmbeddingProvider,
+    jobs::{JobRegistry, JobStore},
+    mapping::MappingConfig,
+    watcher::WatcherRegistry,
+};
 use mongodb::Client as MongoClient;
 use neo4rs::Graph;
 use std::sync::Arc;
@@ -6,22 +15,43 @@ use std::sync::Arc;
 pub struct AppState {
     pub mongo: MongoClient,
     pub graph: Arc<Graph>,
-    pub gemini_key: String,
+    pub embedder: Arc<dyn EmbeddingProvider>,
     pub mongo_db_name: String,
+    pub jobs: JobStore,
+    pub job_registry: JobRegistry,
+    pub ingest: IngestConfig,
+    pub embedding_cache: Option<EmbeddingCache>,
+    pub chunker: Option<Chunker>,
+    pub mappings: Arc<MappingConfig>,
+    pub watchers: WatcherRegistry,
 }
 
 impl AppState {
     pub fn new(
         mongo: MongoClient,
         graph: Arc<Graph>,
-        gemini_key: String,
+        embedder: Arc<dyn EmbeddingProvider>,
         mongo_db_name: String,
+        ingest: IngestConfig,
+        mappings: MappingConfig,
     ) -> Self {
+        let db = mongo.database(&mongo_db_name);
+        let jobs = JobStore::new(&db);
+        let watchers = WatcherRegistry::new(&db);
+        let embedding_cache = ingest.embedding_cache.then(|| EmbeddingCache::new(&db));
+        let chunker = ingest.chunker();
         Self {
             mongo,
             graph,
-            gemini_key,
+            embedder,
             mongo_db_name,
+            jobs,
+            job_registry: JobRegistry::new(&db, ingest.lease_ttl_secs),
+            ingest,
+            embedding_cache,
+            chunker,
+            mappings: Arc::new(mappings),
+            watchers,
         }
     }
 }
@@ -1,33 +1,89 @@
 // src/utils.rs
-use neo4rs::{BoltMap, BoltString, BoltType};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime};
+use neo4rs::{BoltList, BoltMap, BoltNull, BoltString, BoltType};
 use serde_json::Value;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-pub fn bolt_map_from_serde(map: serde_json::Map<String, Value>) -> BoltMap {
-    let mut bolt_map = BoltMap {
-        value: Default::default(),
-    };
+pub fn json_map_to_bolt_type(map: serde_json::Map<String, Value>) -> BoltType {
+    let mut bolt_map = BoltMap::new();
 
-    for (k, v) in map {
-        let key: BoltString = k.into();
+    for (key, value) in map {
+        let bolt_key: BoltString = key.into();
+        let bolt_val: BoltType = match value {
+            // Neo4j solo acepta listas de valores simples como propiedad
+            Value::Array(items) if items.iter().all(is_scalar) => BoltType::List(BoltList::from(
+                items.into_iter().map(scalar_to_bolt).collect::<Vec<_>>(),
+            )),
+            Value::Array(_) | Value::Object(_) => value.to_string().into(),
+            scalar => scalar_to_bolt(scalar),
+        };
+        bolt_map.put(bolt_key, bolt_val);
+    }
+    BoltType::Map(bolt_map)
+}
 
-        let val: Option<BoltType> = match v {
-            Value::String(s) => Some(s.into()),
-            Value::Number(n) => {
-                if let Some(i) = n.as_i64() {
-                    Some(i.into())
-                } else if let Some(f) = n.as_f64() {
-                    Some(f.into())
-                } else {
-                    None
-                }
+fn is_scalar(value: &Value) -> bool {
+    matches!(value, Value::String(_) | Value::Number(_) | Value::Bool(_))
+}
+
+fn scalar_to_bolt(value: Value) -> BoltType {
+    match value {
+        Value::String(s) => s.into(),
+        Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                i.into()
+            } else if let Some(f) = n.as_f64() {
+                f.into()
+            } else {
+                n.to_string().into()
             }
-            Value::Bool(b) => Some(b.into()),
-            _ => None,
-        };
+        }
+        Value::Bool(b) => b.into(),
+        Value::Null => BoltType::Null(BoltNull),
+        other => other.to_string().into(),
+    }
+}
 
-        if let Some(bolt_val) = val {
-            bolt_map.value.insert(key, bolt_val);
+// Valor leído de Neo4j a JSON. Las fechas y horas salen en ISO 8601; lo que no
+// tiene equivalente (nodos, puntos, bytes...) queda en null.
+pub fn bolt_to_json(value: &BoltType) -> Value {
+    match value {
+        BoltType::String(s) => Value::from(s.value.as_str()),
+        BoltType::Boolean(b) => Value::from(b.value),
+        BoltType::Integer(i) => Value::from(i.value),
+        BoltType::Float(f) => Value::from(f.value),
+        BoltType::List(list) => Value::Array(list.value.iter().map(bolt_to_json).collect()),
+        BoltType::Map(map) => Value::Object(
+            map.value
+                .iter()
+                .map(|(key, value)| (key.value.clone(), bolt_to_json(value)))
+                .collect(),
+        ),
+        BoltType::Date(date) => NaiveDate::try_from(date)
+            .map(|d| Value::from(d.to_string()))
+            .unwrap_or(Value::Null),
+        BoltType::LocalDateTime(dt) => NaiveDateTime::try_from(dt)
+            .map(|dt| Value::from(dt.format("%Y-%m-%dT%H:%M:%S%.f").to_string()))
+            .unwrap_or(Value::Null),
+        BoltType::DateTime(dt) => DateTime::<FixedOffset>::try_from(dt)
+            .map(|dt| Value::from(dt.to_rfc3339()))
+            .unwrap_or(Value::Null),
+        BoltType::DateTimeZoneId(dt) => DateTime::<FixedOffset>::try_from(dt)
+            .map(|dt| Value::from(dt.to_rfc3339()))
+            .unwrap_or(Value::Null),
+        BoltType::LocalTime(t) => Value::from(NaiveTime::from(t).to_string()),
+        BoltType::Time(t) => {
+            let (time, offset): (NaiveTime, FixedOffset) = t.into();
+            Value::from(format!("{}{}", time, offset))
         }
+        _ => Value::Null,
     }
-    bolt_map
+}
+
+// Milisegundos desde epoch, mismo formato que `timestamp()` en Cypher
+pub fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
 }
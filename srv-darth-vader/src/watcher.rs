@@ -0,0 +1,676 @@
+use crate::{
+    graph_writer::GraphBatchWriter,
+    jobs::IngestJob,
+    reconcile::{ReconcileMode, remove_by_oids},
+    services::{Decoder, SourceDoc, decoder_for, record_outcomes, spawn_embedding, write_group},
+    state::AppState,
+    utils::now_millis,
+};
+use anyhow::Result;
+use futures::stream::{StreamExt, TryStreamExt};
+use mongodb::{
+    Collection, Database,
+    bson::{Bson, Document, doc, oid::ObjectId, to_bson},
+    change_stream::event::{ChangeStreamEvent, OperationType, ResumeToken},
+    error::ErrorKind,
+    options::FullDocumentType,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio_util::sync::CancellationToken;
+
+pub const WATCHERS_COLLECTION: &str = "change_watchers";
+
+// Espera antes de reabrir un change stream que falló
+const WATCH_RETRY: Duration = Duration::from_secs(5);
+// Reintentos de un lote que no se pudo escribir antes de darlo por fallido y seguir
+const MAX_WRITE_RETRIES: u32 = 5;
+
+// Estado persistido de un watcher; `_id` es el nombre de la colección
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatcherState {
+    #[serde(rename = "_id")]
+    pub collection: String,
+    pub enabled: bool,
+    pub on_delete: ReconcileMode,
+    #[serde(default)]
+    pub resume_token: Option<ResumeToken>,
+    #[serde(default)]
+    pub started_at: Option<i64>,
+    #[serde(default)]
+    pub last_event_at: Option<i64>,
+    #[serde(default)]
+    pub upserted: u64,
+    #[serde(default)]
+    pub deleted: u64,
+    #[serde(default)]
+    pub skipped: u64,
+    #[serde(default)]
+    pub failed: u64,
+    #[serde(default)]
+    pub last_error: Option<String>,
+}
+
+// Vista para la API: el token es opaco, solo interesa si existe
+#[derive(Debug, Serialize)]
+pub struct WatcherStatus {
+    pub collection: String,
+    pub enabled: bool,
+    pub running: bool,
+    pub on_delete: ReconcileMode,
+    pub resumable: bool,
+    pub started_at: Option<i64>,
+    pub last_event_at: Option<i64>,
+    pub upserted: u64,
+    pub deleted: u64,
+    pub skipped: u64,
+    pub failed: u64,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct WatchProgress {
+    upserted: u64,
+    deleted: u64,
+    // Documentos que no decodifican: se saltan, reintentar no los arregla
+    skipped: u64,
+    failed: u64,
+    last_error: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct WatcherStore {
+    collection: Collection<WatcherState>,
+}
+
+impl WatcherStore {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection::<WatcherState>(WATCHERS_COLLECTION),
+        }
+    }
+
+    pub async fn get(&self, collection: &str) -> Result<Option<WatcherState>> {
+        Ok(self.collection.find_one(doc! { "_id": collection }).await?)
+    }
+
+    pub async fn list(&self) -> Result<Vec<WatcherState>> {
+        let cursor = self
+            .collection
+            .find(doc! {})
+            .sort(doc! { "_id": 1 })
+            .await?;
+        Ok(cursor.try_collect().await?)
+    }
+
+    // Conserva el resume token y los contadores de sesiones anteriores
+    async fn enable(&self, collection: &str, on_delete: ReconcileMode) -> Result<()> {
+        self.collection
+            .update_one(
+                doc! { "_id": collection },
+                doc! {
+                    "$set": {
+                        "enabled": true,
+                        "on_delete": to_bson(&on_delete)?,
+                        "started_at": now_millis(),
+                        "last_error": null,
+                    },
+                    "$setOnInsert": {
+                        "upserted": 0_i64,
+                        "deleted": 0_i64,
+                        "skipped": 0_i64,
+                        "failed": 0_i64,
+                    },
+                },
+            )
+            .upsert(true)
+            .await?;
+        Ok(())
+    }
+
+    async fn disable(&self, collection: &str, reason: Option<&str>) -> Result<()> {
+        let mut set = doc! { "enabled": false };
+        if let Some(reason) = reason {
+            // Tras un invalidate el token ya no sirve para retomar
+            set.insert("last_error", reason);
+            set.insert("resume_token", Bson::Null);
+        }
+        self.collection
+            .update_one(doc! { "_id": collection }, doc! { "$set": set })
+            .await?;
+        Ok(())
+    }
+
+    async fn record(
+        &self,
+        collection: &str,
+        token: Option<ResumeToken>,
+        progress: &WatchProgress,
+    ) -> Result<()> {
+        let mut set = doc! { "last_event_at": now_millis() };
+        if let Some(token) = token {
+            set.insert("resume_token", to_bson(&token)?);
+        }
+        if let Some(error) = &progress.last_error {
+            set.insert("last_error", error);
+        }
+        self.collection
+            .update_one(
+                doc! { "_id": collection },
+                doc! {
+                    "$set": set,
+                    "$inc": {
+                        "upserted": progress.upserted as i64,
+                        "deleted": progress.deleted as i64,
+                        "skipped": progress.skipped as i64,
+                        "failed": progress.failed as i64,
+                    },
+                },
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn record_error(&self, collection: &str, error: &str) -> Result<()> {
+        self.collection
+            .update_one(
+                doc! { "_id": collection },
+                doc! { "$set": { "last_error": error } },
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+// Watchers vivos en este proceso, con el token para detenerlos
+#[derive(Clone)]
+pub struct WatcherRegistry {
+    pub store: WatcherStore,
+    running: Arc<Mutex<HashMap<String, CancellationToken>>>,
+}
+
+impl WatcherRegistry {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            store: WatcherStore::new(db),
+            running: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn is_running(&self, collection: &str) -> bool {
+        self.running
+            .lock()
+            .expect("registro de watchers envenenado")
+            .contains_key(collection)
+    }
+
+    pub async fn statuses(&self) -> Result<Vec<WatcherStatus>> {
+        let states = self.store.list().await?;
+        Ok(states
+            .into_iter()
+            .map(|state| WatcherStatus {
+                running: self.is_running(&state.collection),
+                resumable: state.resume_token.is_some(),
+                collection: state.collection,
+                enabled: state.enabled,
+                on_delete: state.on_delete,
+                started_at: state.started_at,
+                last_event_at: state.last_event_at,
+                upserted: state.upserted,
+                deleted: state.deleted,
+                skipped: state.skipped,
+                failed: state.failed,
+                last_error: state.last_error,
+            })
+            .collect())
+    }
+
+    // None si ya había un watcher corriendo para la colección
+    fn claim(&self, collection: &str) -> Option<CancellationToken> {
+        let mut running = self
+            .running
+            .lock()
+            .expect("registro de watchers envenenado");
+        if running.contains_key(collection) {
+            return None;
+        }
+        let token = CancellationToken::new();
+        running.insert(collection.to_string(), token.clone());
+        Some(token)
+    }
+
+    fn release(&self, collection: &str) -> Option<CancellationToken> {
+        self.running
+            .lock()
+            .expect("registro de watchers envenenado")
+            .remove(collection)
+    }
+}
+
+// Un watcher escribe nodos con su propio `ingest_run`: una reconciliación de la
+// misma colección los vería como no tocados y los archivaría o borraría.
+pub async fn watched_collections(state: &AppState, collections: &[String]) -> Result<Vec<String>> {
+    let mut watched = Vec::new();
+    for collection in collections {
+        if let Some(watcher) = state.watchers.store.get(collection).await?
+            && watcher.enabled
+        {
+            watched.push(collection.clone());
+        }
+    }
+    Ok(watched)
+}
+
+// Activa (y persiste) el watcher. Devuelve false si ya estaba corriendo.
+pub async fn start_watcher(
+    state: Arc<AppState>,
+    collection: &str,
+    on_delete: ReconcileMode,
+) -> Result<bool> {
+    let decode =
+        decoder_for(&state, collection).ok_or_else(|| anyhow::anyhow!("Colección no mapeada"))?;
+    state.watchers.store.enable(collection, on_delete).await?;
+    Ok(spawn_watcher(state, collection, decode, on_delete))
+}
+
+// Desactiva el watcher; no vuelve a arrancar con el servicio
+pub async fn stop_watcher(state: &AppState, collection: &str) -> Result<bool> {
+    state.watchers.store.disable(collection, None).await?;
+    Ok(match state.watchers.release(collection) {
+        Some(token) => {
+            token.cancel();
+            true
+        }
+        None => false,
+    })
+}
+
+// Al arrancar el servicio: retoma los watchers activos desde su resume token
+pub async fn resume_watchers(state: Arc<AppState>) -> Result<()> {
+    for watcher in state.watchers.store.list().await? {
+        if !watcher.enabled {
+            continue;
+        }
+        match decoder_for(&state, &watcher.collection) {
+            Some(decode) => {
+                spawn_watcher(
+                    state.clone(),
+                    &watcher.collection,
+                    decode,
+                    watcher.on_delete,
+                );
+            }
+            None => eprintln!(
+                "⚠️ Watcher de {} sin mapping: no se retoma",
+                watcher.collection
+            ),
+        }
+    }
+    Ok(())
+}
+
+fn spawn_watcher(
+    state: Arc<AppState>,
+    collection: &str,
+    decode: Decoder,
+    on_delete: ReconcileMode,
+) -> bool {
+    let Some(cancel) = state.watchers.claim(collection) else {
+        return false;
+    };
+    tokio::spawn(watch_loop(
+        state,
+        collection.to_string(),
+        decode,
+        on_delete,
+        cancel,
+    ));
+    true
+}
+
+async fn watch_loop(
+    state: Arc<AppState>,
+    collection: String,
+    decode: Decoder,
+    on_delete: ReconcileMode,
+    cancel: CancellationToken,
+) {
+    println!("👀 Watcher activo: {}", collection);
+    let store = &state.watchers.store;
+    let mut write_failures = 0;
+
+    loop {
+        match watch_once(
+            &state,
+            &collection,
+            &decode,
+            on_delete,
+            &cancel,
+            &mut write_failures,
+        )
+        .await
+        {
+            Ok(None) => break,
+            // La colección se borró o renombró: el stream no se puede retomar
+            Ok(Some(reason)) => {
+                eprintln!("⚠️ Watcher de {} invalidado: {}", collection, reason);
+                if let Err(e) = store.disable(&collection, Some(&reason)).await {
+                    eprintln!("⚠️ No se pudo desactivar el watcher {}: {}", collection, e);
+                }
+                break;
+            }
+            Err(e) if is_unrecoverable(&e) => {
+                let reason = format!(
+                    "Change stream irrecuperable ({:#}): hace falta una ingesta completa de la colección",
+                    e
+                );
+                eprintln!("⚠️ Watcher de {}: {}", collection, reason);
+                if let Err(e) = store.disable(&collection, Some(&reason)).await {
+                    eprintln!("⚠️ No se pudo desactivar el watcher {}: {}", collection, e);
+                }
+                break;
+            }
+            Err(e) => {
+                eprintln!("⚠️ Change stream de {} falló: {:#}", collection, e);
+                if let Err(e) = store.record_error(&collection, &format!("{:#}", e)).await {
+                    eprintln!("⚠️ No se pudo guardar el error del watcher: {}", e);
+                }
+                tokio::select! {
+                    _ = cancel.cancelled() => break,
+                    _ = tokio::time::sleep(WATCH_RETRY) => {}
+                }
+            }
+        }
+    }
+
+    // Si lo detuvo la API ya salió del registro (y puede haber otro en su lugar)
+    if !cancel.is_cancelled() {
+        state.watchers.release(&collection);
+    }
+    println!("🛑 Watcher detenido: {}", collection);
+}
+
+// Abre el change stream y lo consume hasta que lo cancelen (None) o se invalide (Some).
+// `write_failures` cuenta los reintentos seguidos del mismo lote; al llegar a
+// MAX_WRITE_RETRIES ese lote se aplica igual, contando como fallidas las entidades
+// que no se pudieron escribir, para que un evento envenenado no trabe el watcher.
+async fn watch_once(
+    state: &Arc<AppState>,
+    collection: &str,
+    decode: &Decoder,
+    on_delete: ReconcileMode,
+    cancel: &CancellationToken,
+    write_failures: &mut u32,
+) -> Result<Option<String>> {
+    let store = &state.watchers.store;
+    let resume_token = store.get(collection).await?.and_then(|w| w.resume_token);
+
+    let mut stream = state
+        .mongo
+        .database(&state.mongo_db_name)
+        .collection::<Document>(collection)
+        .watch()
+        .full_document(FullDocumentType::UpdateLookup)
+        .resume_after(resume_token)
+        .await?;
+
+    // Cada apertura del stream cuenta como una corrida para `ingest_run`
+    let run_id = ObjectId::new().to_hex();
+    let mut writer = GraphBatchWriter::new(
+        state.graph.clone(),
+        state.ingest.graph_batch_size,
+        collection,
+        &run_id,
+    )
+    .reconcile_edges(state.ingest.reconcile_edges);
+
+    loop {
+        let next = tokio::select! {
+            _ = cancel.cancelled() => return Ok(None),
+            next = stream.next() => next,
+        };
+        let Some(first) = next else {
+            return Ok(Some("Change stream cerrado por el servidor".to_string()));
+        };
+
+        // Lo que ya llegó se procesa junto para embeber en un solo lote
+        let mut events = vec![first?];
+        while events.len() < state.ingest.graph_batch_size
+            && let Some(event) = stream.next_if_any().await?
+        {
+            events.push(event);
+        }
+
+        let tolerate_failures = *write_failures >= MAX_WRITE_RETRIES;
+        let result = apply_events(
+            state,
+            collection,
+            decode,
+            on_delete,
+            &mut writer,
+            events,
+            tolerate_failures,
+        )
+        .await;
+        let progress = &result.progress;
+        store.record(collection, result.applied, progress).await?;
+        println!(
+            "🔄 {}: {} actualizados, {} borrados, {} inválidos, {} fallidos",
+            collection, progress.upserted, progress.deleted, progress.skipped, progress.failed
+        );
+
+        // El stream se reabre desde el último evento aplicado y reintenta el resto
+        if let Some(error) = result.error {
+            *write_failures += 1;
+            return Err(anyhow::anyhow!(
+                "{} (intento {} de {})",
+                error,
+                write_failures,
+                MAX_WRITE_RETRIES
+            ));
+        }
+        *write_failures = 0;
+        if result.invalidated.is_some() {
+            return Ok(result.invalidated);
+        }
+    }
+}
+
+// Resultado de aplicar un lote de eventos
+struct AppliedEvents {
+    // Contadores solo de los eventos hasta `applied`: lo que se reintenta no suma
+    progress: WatchProgress,
+    // Último evento que quedó aplicado junto con todos los anteriores: hasta
+    // ahí avanza el resume token
+    applied: Option<ResumeToken>,
+    invalidated: Option<String>,
+    // Error de escritura: el resto del lote no se aplica y se reintenta
+    error: Option<String>,
+}
+
+impl WatchProgress {
+    fn absorb(&mut self, other: WatchProgress) {
+        self.upserted += other.upserted;
+        self.deleted += other.deleted;
+        self.skipped += other.skipped;
+        self.failed += other.failed;
+        if other.last_error.is_some() {
+            self.last_error = other.last_error;
+        }
+    }
+}
+
+// Aplica los eventos en orden: altas/cambios se agrupan hasta el siguiente delete.
+// Al primer error de escritura se corta, para no avanzar el token por encima
+// (salvo con `tolerate_failures`, que cuenta el error y sigue).
+async fn apply_events(
+    state: &Arc<AppState>,
+    collection: &str,
+    decode: &Decoder,
+    on_delete: ReconcileMode,
+    writer: &mut GraphBatchWriter,
+    events: Vec<ChangeStreamEvent<Document>>,
+    tolerate_failures: bool,
+) -> AppliedEvents {
+    let mut result = AppliedEvents {
+        progress: WatchProgress::default(),
+        applied: None,
+        invalidated: None,
+        error: None,
+    };
+    let mut upserts: Vec<SourceDoc> = Vec::new();
+    let mut deletes: Vec<String> = Vec::new();
+    // Último evento encolado en `upserts` / `deletes`, y lo contado desde el último aplicado
+    let mut queued: Option<ResumeToken> = None;
+    let mut pending = WatchProgress::default();
+
+    for event in events {
+        let flushed = match event.operation_type {
+            OperationType::Delete => {
+                apply_upserts(state, collection, writer, &mut upserts, &mut pending).await
+            }
+            _ => apply_deletes(state, collection, on_delete, &mut deletes, &mut pending).await,
+        };
+        if let Err(e) = flushed
+            && !tolerate_failures
+        {
+            result.error = Some(e);
+            return result;
+        }
+        if upserts.is_empty()
+            && deletes.is_empty()
+            && let Some(token) = queued.take()
+        {
+            result.applied = Some(token);
+            result.progress.absorb(std::mem::take(&mut pending));
+        }
+
+        match event.operation_type {
+            OperationType::Insert | OperationType::Update | OperationType::Replace => {
+                // Un update sin documento: se borró antes del lookup, llega su delete
+                if let Some(doc) = event.full_document {
+                    match decode(doc) {
+                        Ok(doc) => upserts.push(doc),
+                        // No se arregla reintentando: se cuenta y se sigue
+                        Err(e) => {
+                            eprintln!("⚠️ Documento inválido en {}: {}", collection, e);
+                            pending.skipped += 1;
+                            pending.last_error = Some(e.to_string());
+                        }
+                    }
+                }
+            }
+            OperationType::Delete => {
+                if let Some(oid) = event
+                    .document_key
+                    .as_ref()
+                    .and_then(|key| key.get_object_id("_id").ok())
+                {
+                    deletes.push(oid.to_hex());
+                }
+            }
+            OperationType::Drop
+            | OperationType::Rename
+            | OperationType::DropDatabase
+            | OperationType::Invalidate => {
+                result.invalidated = Some(format!("Evento {:?}", event.operation_type));
+            }
+            _ => {}
+        }
+        queued = Some(event.id);
+
+        if result.invalidated.is_some() {
+            break;
+        }
+    }
+
+    let mut flushed = apply_upserts(state, collection, writer, &mut upserts, &mut pending).await;
+    if flushed.is_ok() || tolerate_failures {
+        flushed = apply_deletes(state, collection, on_delete, &mut deletes, &mut pending).await;
+    }
+    match flushed {
+        Err(e) if !tolerate_failures => result.error = Some(e),
+        _ => {
+            if let Some(token) = queued {
+                result.applied = Some(token);
+                result.progress.absorb(pending);
+            }
+        }
+    }
+    result
+}
+
+// Mismo camino que la ingesta completa: embeddings (con cache) y writer por lotes.
+// Err si alguna entidad no se pudo escribir; igual quedan contadas en `progress`.
+async fn apply_upserts(
+    state: &Arc<AppState>,
+    collection: &str,
+    writer: &mut GraphBatchWriter,
+    upserts: &mut Vec<SourceDoc>,
+    progress: &mut WatchProgress,
+) -> Result<(), String> {
+    if upserts.is_empty() {
+        return Ok(());
+    }
+
+    // Job de trabajo solo para reutilizar los contadores; no se persiste
+    let mut scratch = IngestJob::new(collection);
+    match spawn_embedding(state.clone(), std::mem::take(upserts)).await {
+        Ok(group) => {
+            write_group(&mut scratch, writer, group).await;
+            record_outcomes(&mut scratch, writer.flush().await);
+        }
+        Err(e) => scratch.record_failure(format!("Embedding: {}", e)),
+    }
+
+    progress.upserted += scratch.processed;
+    progress.failed += scratch.failed;
+    match scratch.last_error {
+        Some(e) if scratch.failed > 0 => {
+            progress.last_error = Some(e.clone());
+            Err(e)
+        }
+        _ => Ok(()),
+    }
+}
+
+async fn apply_deletes(
+    state: &AppState,
+    collection: &str,
+    on_delete: ReconcileMode,
+    deletes: &mut Vec<String>,
+    progress: &mut WatchProgress,
+) -> Result<(), String> {
+    if deletes.is_empty() {
+        return Ok(());
+    }
+
+    let oids = std::mem::take(deletes);
+    match remove_by_oids(&state.graph, collection, &oids, on_delete).await {
+        Ok(removed) => {
+            progress.deleted += removed as u64;
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("❌ Error propagando borrados de {}: {}", collection, e);
+            progress.failed += oids.len() as u64;
+            progress.last_error = Some(e.to_string());
+            Err(e.to_string())
+        }
+    }
+}
+
+// El resume token ya no está en el oplog (o el stream quedó inutilizable):
+// reintentar con el mismo token falla siempre
+fn is_unrecoverable(error: &anyhow::Error) -> bool {
+    // ChangeStreamHistoryLost, ChangeStreamFatalError, InvalidResumeToken
+    const CODES: [i32; 3] = [286, 280, 260];
+    matches!(
+        error.downcast_ref::<mongodb::error::Error>().map(|e| e.kind.as_ref()),
+        Some(ErrorKind::Command(e)) if CODES.contains(&e.code)
+    )
+}